@@ -15,7 +15,7 @@ fn watch<P: AsRef<Path>>(path: P) -> notify::Result<()> {
 
     // Add a path to be watched. All files and directories at that path and
     // below will be monitored for changes.
-    try!(watcher.watch(path, RecursiveMode::Recursive));
+    let _watch = try!(watcher.watch(path, RecursiveMode::Recursive));
 
     // This is a simple loop, but you may want to use more complex logic here,
     // for example to handle I/O.
@@ -0,0 +1,297 @@
+use std::time::Duration;
+use super::{Error, Result, SymlinkPolicy};
+
+/// Names of all options
+#[cfg(feature = "serde")]
+const OPTIONS: &'static [&'static str] = &["poll_interval",
+                                           "debounce_delay",
+                                           "notice_write",
+                                           "notice_remove",
+                                           "symlink_policy",
+                                           "pending_watches",
+                                           "persistent_roots",
+                                           "relative_paths",
+                                           "rename_timeout",
+                                           "access_events",
+                                           "thread_name"];
+
+/// Options that only apply to watchers in debounced mode
+const DEBOUNCE_OPTIONS: &'static [&'static str] = &["debounce_delay",
+                                                    "notice_write",
+                                                    "notice_remove"];
+
+/// Options that only apply to watchers in raw mode
+const RAW_OPTIONS: &'static [&'static str] = &["relative_paths"];
+
+/// Configuration of a `Watcher`
+///
+/// Passed to [`Watcher::new_raw_with_config`](trait.Watcher.html#method.new_raw_with_config)
+/// or [`Watcher::new_with_config`](trait.Watcher.html#method.new_with_config). Options that
+/// haven't been set keep the default behavior of the backend. Setting an option the backend (or
+/// the mode of the watcher) doesn't support results in an `Error::UnsupportedOption` instead of
+/// the option being ignored.
+///
+/// # Example
+///
+/// ```no_run
+/// use notify::{Config, RecommendedWatcher, Watcher};
+/// use std::sync::mpsc::channel;
+/// use std::time::Duration;
+///
+/// let (tx, rx) = channel();
+/// let config = Config::new()
+///     .with_debounce_delay(Duration::from_secs(2))
+///     .with_notice_write(false)
+///     .with_thread_name("config watcher");
+/// let watcher: RecommendedWatcher = Watcher::new_with_config(tx, config).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    poll_interval: Option<Duration>,
+    debounce_delay: Option<Duration>,
+    notice_write: Option<bool>,
+    notice_remove: Option<bool>,
+    symlink_policy: Option<SymlinkPolicy>,
+    pending_watches: Option<bool>,
+    persistent_roots: Option<bool>,
+    relative_paths: Option<bool>,
+    rename_timeout: Option<Duration>,
+    access_events: Option<bool>,
+    thread_name: Option<String>,
+}
+
+impl Config {
+    /// Create a configuration without any options set
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    /// Set the interval in which the `PollWatcher` checks the watched paths.
+    ///
+    /// Supported by the `PollWatcher` only. Defaults to 30 seconds in raw mode and to the debounce
+    /// delay in debounced mode.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Config {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Set the delay used to debounce events.
+    ///
+    /// Required in debounced mode, not supported in raw mode.
+    pub fn with_debounce_delay(mut self, delay: Duration) -> Config {
+        self.debounce_delay = Some(delay);
+        self
+    }
+
+    /// Emit `DebouncedEvent::NoticeWrite` events.
+    ///
+    /// Debounced mode only. Defaults to `true`.
+    pub fn with_notice_write(mut self, enabled: bool) -> Config {
+        self.notice_write = Some(enabled);
+        self
+    }
+
+    /// Emit `DebouncedEvent::NoticeRemove` events.
+    ///
+    /// Debounced mode only. Defaults to `true`.
+    pub fn with_notice_remove(mut self, enabled: bool) -> Config {
+        self.notice_remove = Some(enabled);
+        self
+    }
+
+    /// Set how symbolic links are handled when adding watches for the contents of a directory.
+    ///
+    /// Supported by the `INotifyWatcher` and the `PollWatcher`. Defaults to
+    /// `SymlinkPolicy::Follow`.
+    pub fn with_symlink_policy(mut self, policy: SymlinkPolicy) -> Config {
+        self.symlink_policy = Some(policy);
+        self
+    }
+
+    /// Accept paths that don't exist yet in `watch`.
+    ///
+    /// The nearest existing ancestor of such a path is watched in its place, once the path has
+    /// been created it is watched like any other path and a `CREATE` event is emitted for it.
+    /// Supported by the `INotifyWatcher` and the `PollWatcher`. Defaults to `false`.
+    pub fn with_pending_watches(mut self, enabled: bool) -> Config {
+        self.pending_watches = Some(enabled);
+        self
+    }
+
+    /// Keep watching a watched path after it has been removed.
+    ///
+    /// The nearest existing ancestor of the path is watched in its place until it has been
+    /// recreated. The removal is reported as `REMOVE`, the path reappearing as `CREATE`, changes
+    /// made in between aren't reported. Supported by the `INotifyWatcher` and the `PollWatcher`.
+    /// Defaults to `false`.
+    pub fn with_persistent_roots(mut self, enabled: bool) -> Config {
+        self.persistent_roots = Some(enabled);
+        self
+    }
+
+    /// Report the watched path of each event and the event's path relative to it in
+    /// `RawEvent::relative`.
+    ///
+    /// Raw mode only. Defaults to `false`.
+    pub fn with_relative_paths(mut self, enabled: bool) -> Config {
+        self.relative_paths = Some(enabled);
+        self
+    }
+
+    /// Set how long a move out of a watched directory waits for the matching move into one.
+    ///
+    /// The two halves of a rename may be read separately, a `RENAME` without a match is reported as
+    /// `REMOVE` once the timeout has passed. Supported by the `INotifyWatcher` only. Defaults to
+    /// 50 milliseconds.
+    pub fn with_rename_timeout(mut self, timeout: Duration) -> Config {
+        self.rename_timeout = Some(timeout);
+        self
+    }
+
+    /// Report `ACCESS`, `OPEN` and `CLOSE_NOWRITE` events for all watched paths.
+    ///
+    /// They can be enabled for single watches with
+    /// [`INotifyWatcher::watch_with_mask`](struct.INotifyWatcher.html#method.watch_with_mask) as
+    /// well. Supported by the `INotifyWatcher` only. Defaults to `false`.
+    pub fn with_access_events(mut self, enabled: bool) -> Config {
+        self.access_events = Some(enabled);
+        self
+    }
+
+    /// Set the name of the threads spawned by the watcher.
+    pub fn with_thread_name<S: Into<String>>(mut self, name: S) -> Config {
+        self.thread_name = Some(name.into());
+        self
+    }
+
+    /// Polling interval, if set
+    pub fn poll_interval(&self) -> Option<Duration> {
+        self.poll_interval
+    }
+
+    /// Debounce delay, if set
+    pub fn debounce_delay(&self) -> Option<Duration> {
+        self.debounce_delay
+    }
+
+    /// Whether `NoticeWrite` events are emitted, if set
+    pub fn notice_write(&self) -> Option<bool> {
+        self.notice_write
+    }
+
+    /// Whether `NoticeRemove` events are emitted, if set
+    pub fn notice_remove(&self) -> Option<bool> {
+        self.notice_remove
+    }
+
+    /// Handling of symbolic links, if set
+    pub fn symlink_policy(&self) -> Option<SymlinkPolicy> {
+        self.symlink_policy
+    }
+
+    /// Whether paths that don't exist yet can be watched, if set
+    pub fn pending_watches(&self) -> Option<bool> {
+        self.pending_watches
+    }
+
+    /// Whether removed watched paths are watched again once they have been recreated, if set
+    pub fn persistent_roots(&self) -> Option<bool> {
+        self.persistent_roots
+    }
+
+    /// Whether events carry paths relative to the watched path, if set
+    pub fn relative_paths(&self) -> Option<bool> {
+        self.relative_paths
+    }
+
+    /// Time a move waits for its matching half, if set
+    pub fn rename_timeout(&self) -> Option<Duration> {
+        self.rename_timeout
+    }
+
+    /// Whether access events are reported for all watched paths, if set
+    pub fn access_events(&self) -> Option<bool> {
+        self.access_events
+    }
+
+    /// Name of the watcher's threads, if set
+    pub fn thread_name(&self) -> Option<&str> {
+        self.thread_name.as_ref().map(|name| name.as_ref())
+    }
+
+    /// Names of the options that have been set
+    fn options(&self) -> Vec<&'static str> {
+        let mut options = Vec::new();
+        if self.poll_interval.is_some() {
+            options.push("poll_interval");
+        }
+        if self.debounce_delay.is_some() {
+            options.push("debounce_delay");
+        }
+        if self.notice_write.is_some() {
+            options.push("notice_write");
+        }
+        if self.notice_remove.is_some() {
+            options.push("notice_remove");
+        }
+        if self.symlink_policy.is_some() {
+            options.push("symlink_policy");
+        }
+        if self.pending_watches.is_some() {
+            options.push("pending_watches");
+        }
+        if self.persistent_roots.is_some() {
+            options.push("persistent_roots");
+        }
+        if self.relative_paths.is_some() {
+            options.push("relative_paths");
+        }
+        if self.rename_timeout.is_some() {
+            options.push("rename_timeout");
+        }
+        if self.access_events.is_some() {
+            options.push("access_events");
+        }
+        if self.thread_name.is_some() {
+            options.push("thread_name");
+        }
+        options
+    }
+}
+
+/// Fail with `Error::UnsupportedOption` if `config` sets an option that isn't `supported`.
+///
+/// The debounce options are supported implicitly if `debounced` is true, the raw options are
+/// never supported in debounced mode.
+pub fn check_supported(config: &Config, supported: &[&str], debounced: bool) -> Result<()> {
+    for option in config.options() {
+        if !supported.contains(&option) && !(debounced && DEBOUNCE_OPTIONS.contains(&option)) {
+            return Err(Error::UnsupportedOption(option));
+        }
+        if debounced && RAW_OPTIONS.contains(&option) {
+            return Err(Error::UnsupportedOption(option));
+        }
+    }
+
+    Ok(())
+}
+
+/// Look up the name of an option
+#[cfg(feature = "serde")]
+pub fn option_name(name: &str) -> Option<&'static str> {
+    OPTIONS.iter().cloned().find(|option| *option == name)
+}
+
+/// Copy of `config` without the options that only apply to the `PollWatcher`
+pub fn without_poll_options(config: &Config) -> Config {
+    Config { poll_interval: None, ..config.clone() }
+}
+
+/// Copy of `config` without the options that only apply to the native watchers
+pub fn without_native_options(config: &Config) -> Config {
+    Config {
+        rename_timeout: None,
+        access_events: None,
+        ..config.clone()
+    }
+}
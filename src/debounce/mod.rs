@@ -2,11 +2,11 @@
 
 mod timer;
 
-use super::{op, RawEvent, DebouncedEvent};
+use super::{op, Config, Error, Result, RawEvent, DebouncedEvent, EventHandler, EventKind};
+use super::filter::{self, WatchFilters};
 
 use self::timer::WatchTimer;
 
-use std::sync::mpsc;
 use std::path::PathBuf;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
@@ -15,79 +15,281 @@ use std::time::Duration;
 pub type OperationsBuffer = Arc<Mutex<HashMap<PathBuf,
                                               (Option<op::Op>, Option<PathBuf>, Option<u64>)>>>;
 
-pub enum EventTx {
-    Raw { tx: mpsc::Sender<RawEvent> },
+struct DebouncedHandler {
+    handler: Box<EventHandler<DebouncedEvent>>,
+    paused: bool,
+    /// Whether events have been discarded since the watcher has been paused
+    missed: bool,
+}
+
+/// Handler for debounced events, shared between the watcher and the debouncing thread
+///
+/// Pausing applies to the events fired by the debouncing thread as well.
+#[derive(Clone)]
+pub struct DebouncedTx(Arc<Mutex<DebouncedHandler>>);
+
+impl DebouncedTx {
+    pub fn new<H: EventHandler<DebouncedEvent>>(handler: H) -> DebouncedTx {
+        DebouncedTx(Arc::new(Mutex::new(DebouncedHandler {
+            handler: Box::new(handler),
+            paused: false,
+            missed: false,
+        })))
+    }
+
+    pub fn send(&self, event: DebouncedEvent) {
+        if let Ok(mut tx) = self.0.lock() {
+            if tx.paused {
+                tx.missed = true;
+            } else {
+                tx.handler.handle_event(event);
+            }
+        }
+    }
+
+    pub fn send_all(&self, events: Vec<DebouncedEvent>) {
+        if events.is_empty() {
+            return;
+        }
+        if let Ok(mut tx) = self.0.lock() {
+            if tx.paused {
+                tx.missed = true;
+            } else {
+                tx.handler.handle_events(events);
+            }
+        }
+    }
+
+    /// Discard all events until `resume` is called
+    pub fn pause(&self) {
+        if let Ok(mut tx) = self.0.lock() {
+            tx.paused = true;
+        }
+    }
+
+    /// Deliver events again, starting with a `Rescan` if events have been discarded here or
+    /// before they have been debounced (`missed`)
+    pub fn resume(&self, missed: bool) {
+        if let Ok(mut tx) = self.0.lock() {
+            tx.paused = false;
+            if tx.missed || missed {
+                tx.missed = false;
+                tx.handler.handle_event(DebouncedEvent::Rescan);
+            }
+        }
+    }
+}
+
+enum Delivery {
+    Raw { tx: Box<EventHandler<RawEvent>> },
     Debounced {
-        tx: mpsc::Sender<DebouncedEvent>,
+        tx: DebouncedTx,
         debounce: Debounce,
     },
-    DebouncedTx { tx: mpsc::Sender<DebouncedEvent> },
+}
+
+/// Delivers the events of a watcher to its handler, applying the filters of the watches
+pub struct EventTx {
+    delivery: Delivery,
+    filters: WatchFilters,
+    paused: bool,
+    /// Whether events have been discarded since the watcher has been paused
+    missed: bool,
+    /// Whether events carry their path relative to the watched path
+    relative_paths: bool,
 }
 
 impl EventTx {
+    pub fn new_raw<H: EventHandler<RawEvent>>(handler: H, config: &Config) -> EventTx {
+        EventTx {
+            delivery: Delivery::Raw { tx: Box::new(handler) },
+            filters: WatchFilters::default(),
+            paused: false,
+            missed: false,
+            relative_paths: config.relative_paths().unwrap_or(false),
+        }
+    }
+
+    pub fn new_debounced<H: EventHandler<DebouncedEvent>>(handler: H,
+                                                          config: &Config)
+                                                          -> Result<EventTx> {
+        let delay = match config.debounce_delay() {
+            Some(delay) => delay,
+            None => {
+                return Err(Error::Generic("a debounce delay is required in debounced mode"
+                    .to_owned()))
+            }
+        };
+        let tx = DebouncedTx::new(handler);
+        Ok(EventTx {
+            delivery: Delivery::Debounced {
+                tx: tx.clone(),
+                debounce: try!(Debounce::new(delay, tx, config)),
+            },
+            filters: WatchFilters::default(),
+            paused: false,
+            missed: false,
+            relative_paths: false,
+        })
+    }
+
+    pub fn is_debounced(&self) -> bool {
+        match self.delivery {
+            Delivery::Raw { .. } => false,
+            Delivery::Debounced { .. } => true,
+        }
+    }
+
+    pub fn filters(&self) -> &WatchFilters {
+        &self.filters
+    }
+
+    pub fn filters_mut(&mut self) -> &mut WatchFilters {
+        &mut self.filters
+    }
+
+    /// Discard all events until `resume` is called
+    pub fn pause(&mut self) {
+        self.paused = true;
+        // events that are being debounced are due while the watcher is paused
+        if let Delivery::Debounced { ref tx, .. } = self.delivery {
+            tx.pause();
+        }
+    }
+
+    /// Deliver events again, starting with a `RESCAN` if events have been discarded
+    pub fn resume(&mut self) {
+        if !self.paused {
+            return;
+        }
+        self.paused = false;
+        let missed = self.missed;
+        self.missed = false;
+        match self.delivery {
+            Delivery::Raw { ref mut tx } => {
+                if missed {
+                    tx.handle_event(RawEvent {
+                        path: None,
+                        op: Ok(op::RESCAN),
+                        kind: EventKind::Rescan,
+                        cookie: None,
+                        relative: None,
+                    });
+                }
+            }
+            Delivery::Debounced { ref tx, .. } => tx.resume(missed),
+        }
+    }
+
+    /// Deliver the events that are held back by the debouncer
+    pub fn flush(&mut self) {
+        if let Delivery::Debounced { ref mut debounce, .. } = self.delivery {
+            debounce.flush();
+        }
+    }
+
     pub fn send(&mut self, event: RawEvent) {
-        match *self {
-            EventTx::Raw { ref tx } => {
-                let _ = tx.send(event);
+        self.send_all(vec![event]);
+    }
+
+    /// Deliver the events a watcher received at once, as a single batch in raw mode
+    pub fn send_all(&mut self, mut events: Vec<RawEvent>) {
+        events.retain(|event| self.accepts(event));
+
+        if self.paused {
+            if !events.is_empty() {
+                self.missed = true;
             }
-            EventTx::Debounced { ref tx, ref mut debounce } => {
-                match (event.path, event.op, event.cookie) {
-                    (None, Ok(op::RESCAN), None) => {
-                        let _ = tx.send(DebouncedEvent::Rescan);
-                    }
-                    (Some(path), Ok(op), cookie) => {
-                        debounce.event(path, op, cookie);
-                    }
-                    (None, Ok(_op), _cookie) => {
-                        // TODO panic!("path is None: {:?} ({:?})", _op, _cookie);
-                    }
-                    (path, Err(e), _) => {
-                        let _ = tx.send(DebouncedEvent::Error(e, path));
-                    }
+            return;
+        }
+
+        if self.relative_paths {
+            for event in &mut events {
+                event.relative = event.path
+                    .as_ref()
+                    .and_then(|path| self.filters.relative(path))
+                    .map(|(root, relative)| (root.to_owned(), relative.to_owned()));
+            }
+        }
+
+        match self.delivery {
+            Delivery::Raw { ref mut tx } => {
+                if !events.is_empty() {
+                    tx.handle_events(events);
                 }
             }
-            EventTx::DebouncedTx { ref tx } => {
-                match (event.path, event.op, event.cookie) {
-                    (None, Ok(op::RESCAN), None) => {
-                        let _ = tx.send(DebouncedEvent::Rescan);
-                    }
-                    (Some(_path), Ok(_op), _cookie) => {
-                        // TODO debounce.event(_path, _op, _cookie);
-                    }
-                    (None, Ok(_op), _cookie) => {
-                        // TODO panic!("path is None: {:?} ({:?})", _op, _cookie);
-                    }
-                    (path, Err(e), _) => {
-                        let _ = tx.send(DebouncedEvent::Error(e, path));
+            Delivery::Debounced { ref tx, ref mut debounce } => {
+                for event in events {
+                    match (event.path, event.op, event.cookie) {
+                        (None, Ok(op::RESCAN), None) => {
+                            tx.send(DebouncedEvent::Rescan);
+                        }
+                        (Some(path), Ok(op), cookie) => {
+                            debounce.event(path, op, cookie);
+                        }
+                        (None, Ok(_op), _cookie) => {
+                            // TODO panic!("path is None: {:?} ({:?})", _op, _cookie);
+                        }
+                        (path, Err(e), _) => {
+                            tx.send(DebouncedEvent::Error(e, path));
+                        }
                     }
                 }
             }
         }
     }
+
+    /// Whether an event passes the filters of the watches
+    fn accepts(&mut self, event: &RawEvent) -> bool {
+        if let Some(ref path) = event.path {
+            if filter::is_ignore_file(path) {
+                self.filters.ignore_file_changed(path);
+            }
+            if !self.filters.matches(path) {
+                return false;
+            }
+        }
+        true
+    }
 }
 
 pub struct Debounce {
-    tx: mpsc::Sender<DebouncedEvent>,
+    tx: DebouncedTx,
     operations_buffer: OperationsBuffer,
     rename_path: Option<PathBuf>,
     rename_cookie: Option<u32>,
     timer: WatchTimer,
+    notice_write: bool,
+    notice_remove: bool,
 }
 
 impl Debounce {
-    pub fn new(delay: Duration, tx: mpsc::Sender<DebouncedEvent>) -> Debounce {
+    pub fn new(delay: Duration, tx: DebouncedTx, config: &Config) -> Result<Debounce> {
         let operations_buffer: OperationsBuffer = Arc::new(Mutex::new(HashMap::new()));
 
         // spawns new thread
-        let timer = WatchTimer::new(tx.clone(), operations_buffer.clone(), delay);
+        let timer = try!(WatchTimer::new(tx.clone(),
+                                         operations_buffer.clone(),
+                                         delay,
+                                         config.thread_name()));
 
-        Debounce {
+        Ok(Debounce {
             tx: tx,
             operations_buffer: operations_buffer,
             rename_path: None,
             rename_cookie: None,
             timer: timer,
-        }
+            notice_write: config.notice_write().unwrap_or(true),
+            notice_remove: config.notice_remove().unwrap_or(true),
+        })
+    }
+
+    /// Deliver all pending events without waiting for the delay to pass
+    pub fn flush(&mut self) {
+        // a rename whose destination hasn't been reported yet is delivered as a partial rename
+        self.rename_path = None;
+        self.rename_cookie = None;
+        self.timer.flush();
     }
 
     fn check_partial_rename(&mut self, path: PathBuf, op: op::Op, cookie: Option<u32>) {
@@ -135,7 +337,9 @@ impl Debounce {
                             Some(op::WRITE) | // change to remove event
                             Some(op::CHMOD) => { // change to remove event
                                 *operation = Some(op::REMOVE);
-                                let _ = self.tx.send(DebouncedEvent::NoticeRemove(path.clone()));
+                                if self.notice_remove {
+                                    self.tx.send(DebouncedEvent::NoticeRemove(path.clone()));
+                                }
                                 restart_timer(timer_id, path, &mut self.timer);
                             }
                             Some(op::RENAME) => {
@@ -162,7 +366,28 @@ impl Debounce {
 
     pub fn event(&mut self, path: PathBuf, mut op: op::Op, cookie: Option<u32>) {
         if op.contains(op::RESCAN) {
-            let _ = self.tx.send(DebouncedEvent::Rescan);
+            self.tx.send(DebouncedEvent::Rescan);
+        }
+
+        // access events don't change the path, there is nothing to wait for
+        if op.contains(op::OPEN) {
+            self.tx.send(DebouncedEvent::Open(path.clone()));
+        }
+        if op.contains(op::ACCESS) {
+            self.tx.send(DebouncedEvent::Access(path.clone()));
+        }
+        if op.contains(op::CLOSE_NOWRITE) {
+            self.tx.send(DebouncedEvent::CloseNowrite(path.clone()));
+        }
+        if op.contains(op::UNMOUNTED) {
+            self.tx.send(DebouncedEvent::Unmounted(path.clone()));
+        }
+        if op.contains(op::WATCH_REMOVED) {
+            self.tx.send(DebouncedEvent::WatchRemoved(path.clone()));
+        }
+        op.remove(op::ACCESS | op::OPEN | op::CLOSE_NOWRITE | op::WATCH_REMOVED | op::UNMOUNTED);
+        if op.is_empty() {
+            return;
         }
 
         if self.rename_path.is_some() {
@@ -237,7 +462,9 @@ impl Debounce {
                     // operations_buffer entry didn't exist
                     None => {
                         *operation = Some(op::WRITE);
-                        let _ = self.tx.send(DebouncedEvent::NoticeWrite(path.clone()));
+                        if self.notice_write {
+                            self.tx.send(DebouncedEvent::NoticeWrite(path.clone()));
+                        }
                         restart_timer(timer_id, path.clone(), &mut self.timer);
                     }
 
@@ -350,14 +577,18 @@ impl Debounce {
 
                         // keep chmod event
                         Some(op::CHMOD) => {
-                            let _ = self.tx.send(DebouncedEvent::NoticeRemove(path.clone()));
+                            if self.notice_remove {
+                                self.tx.send(DebouncedEvent::NoticeRemove(path.clone()));
+                            }
                             restart_timer(timer_id, path.clone(), &mut self.timer);
                         }
 
                         // operations_buffer entry didn't exist
                         None => {
                             *operation = Some(op::RENAME);
-                            let _ = self.tx.send(DebouncedEvent::NoticeRemove(path.clone()));
+                            if self.notice_remove {
+                                self.tx.send(DebouncedEvent::NoticeRemove(path.clone()));
+                            }
                             restart_timer(timer_id, path.clone(), &mut self.timer);
                         }
 
@@ -395,7 +626,9 @@ impl Debounce {
                         // operations_buffer entry didn't exist
                         None => {
                             *operation = Some(op::REMOVE);
-                            let _ = self.tx.send(DebouncedEvent::NoticeRemove(path.clone()));
+                            if self.notice_remove {
+                                self.tx.send(DebouncedEvent::NoticeRemove(path.clone()));
+                            }
                             restart_timer(timer_id, path.clone(), &mut self.timer);
                         }
 
@@ -1,18 +1,18 @@
-use super::super::{op, DebouncedEvent};
+use super::super::{op, DebouncedEvent, Error, Result};
 
 use std::sync::mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
-use std::sync::{Arc, Condvar, Mutex};
 use std::collections::{BinaryHeap, HashSet};
 use std::path::PathBuf;
 use std::cmp::Ordering;
 
-use debounce::OperationsBuffer;
+use debounce::{OperationsBuffer, DebouncedTx};
 
 enum Action {
     Schedule(ScheduledEvent),
     Ignore(u64),
+    Flush(mpsc::Sender<()>),
 }
 
 #[derive(PartialEq, Eq)]
@@ -35,22 +35,19 @@ impl PartialOrd for ScheduledEvent {
 }
 
 struct ScheduleWorker {
-    trigger: Arc<Condvar>,
     request_source: mpsc::Receiver<Action>,
     schedule: BinaryHeap<ScheduledEvent>,
     ignore: HashSet<u64>,
-    tx: mpsc::Sender<DebouncedEvent>,
+    tx: DebouncedTx,
     operations_buffer: OperationsBuffer,
 }
 
 impl ScheduleWorker {
-    fn new(trigger: Arc<Condvar>,
-           request_source: mpsc::Receiver<Action>,
-           tx: mpsc::Sender<DebouncedEvent>,
+    fn new(request_source: mpsc::Receiver<Action>,
+           tx: DebouncedTx,
            operations_buffer: OperationsBuffer)
            -> ScheduleWorker {
         ScheduleWorker {
-            trigger: trigger,
             request_source: request_source,
             schedule: BinaryHeap::new(),
             ignore: HashSet::new(),
@@ -59,19 +56,32 @@ impl ScheduleWorker {
         }
     }
 
-    fn drain_request_queue(&mut self) {
-        while let Ok(action) = self.request_source.try_recv() {
-            match action {
-                Action::Schedule(event) => self.schedule.push(event),
-                Action::Ignore(ignore_id) => {
-                    for &ScheduledEvent { ref id, .. } in &self.schedule {
-                        if *id == ignore_id {
-                            self.ignore.insert(ignore_id);
-                            break;
-                        }
+    fn handle_action(&mut self, action: Action) {
+        match action {
+            Action::Schedule(event) => self.schedule.push(event),
+            Action::Ignore(ignore_id) => {
+                for &ScheduledEvent { ref id, .. } in &self.schedule {
+                    if *id == ignore_id {
+                        self.ignore.insert(ignore_id);
+                        break;
                     }
                 }
             }
+            Action::Flush(tx) => {
+                // fire everything that has been scheduled so far, no matter when it's due
+                let mut events = Vec::new();
+                while !self.schedule.is_empty() {
+                    self.fire_event(&mut events);
+                }
+                self.tx.send_all(events);
+                let _ = tx.send(());
+            }
+        }
+    }
+
+    fn drain_request_queue(&mut self) {
+        while let Ok(action) = self.request_source.try_recv() {
+            self.handle_action(action);
         }
     }
 
@@ -83,14 +93,14 @@ impl ScheduleWorker {
         }
     }
 
-    fn fire_event(&mut self) {
+    fn fire_event(&mut self, events: &mut Vec<DebouncedEvent>) {
         if let Some(ScheduledEvent { id, path, .. }) = self.schedule.pop() {
             if !self.ignore.remove(&id) {
                 if let Ok(ref mut op_buf) = self.operations_buffer.lock() {
                     if let Some((op, from_path, _)) = op_buf.remove(&path) {
                         let is_partial_rename = from_path.is_none();
                         if let Some(from_path) = from_path {
-                            self.tx.send(DebouncedEvent::Rename(from_path, path.clone())).unwrap();
+                            events.push(DebouncedEvent::Rename(from_path, path.clone()));
                         }
                         let message = match op {
                             Some(op::CREATE) => Some(DebouncedEvent::Create(path)),
@@ -107,7 +117,7 @@ impl ScheduleWorker {
                             _ => None,
                         };
                         if let Some(m) = message {
-                            let _ = self.tx.send(m);
+                            events.push(m);
                         }
                     } else {
                         // TODO error!("path not found in operations_buffer: {}", path.display())
@@ -129,28 +139,44 @@ impl ScheduleWorker {
     }
 
     fn run(&mut self) {
-        let m = Mutex::new(());
-
-        // Unwrapping is safe because the mutex can't be poisoned,
-        // since we just created it.
-        let mut g = m.lock().unwrap();
-
         loop {
             self.drain_request_queue();
 
+            // the events that are due at the same time are delivered as one batch
+            let mut events = Vec::new();
             while self.has_event_now() {
-                self.fire_event();
+                self.fire_event(&mut events);
             }
+            self.tx.send_all(events);
+
+            // wait for the next request or until the next event is due
+            let action = match self.duration_until_next_event() {
+                Some(wait_duration) => {
+                    match self.request_source.recv_timeout(wait_duration) {
+                        Ok(action) => Some(action),
+                        Err(mpsc::RecvTimeoutError::Timeout) => None,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                None => {
+                    match self.request_source.recv() {
+                        Ok(action) => Some(action),
+                        Err(_) => break,
+                    }
+                }
+            };
 
-            let wait_duration = self.duration_until_next_event();
+            if let Some(action) = action {
+                self.handle_action(action);
+            }
+        }
 
-            // Unwrapping is safe because the mutex can't be poisoned,
-            // since we haven't shared it with another thread.
-            g = if let Some(wait_duration) = wait_duration {
-                self.trigger.wait_timeout(g, wait_duration).unwrap().0
-            } else {
-                self.trigger.wait(g).unwrap()
-            };
+        // the watcher is gone, deliver the remaining events before the handler is dropped
+        while let Some(wait_duration) = self.duration_until_next_event() {
+            thread::sleep(wait_duration);
+            let mut events = Vec::new();
+            self.fire_event(&mut events);
+            self.tx.send_all(events);
         }
     }
 }
@@ -158,29 +184,33 @@ impl ScheduleWorker {
 pub struct WatchTimer {
     counter: u64,
     schedule_tx: mpsc::Sender<Action>,
-    trigger: Arc<Condvar>,
     delay: Duration,
 }
 
 impl WatchTimer {
-    pub fn new(tx: mpsc::Sender<DebouncedEvent>,
+    pub fn new(tx: DebouncedTx,
                operations_buffer: OperationsBuffer,
-               delay: Duration)
-               -> WatchTimer {
+               delay: Duration,
+               thread_name: Option<&str>)
+               -> Result<WatchTimer> {
         let (schedule_tx, schedule_rx) = mpsc::channel();
-        let trigger = Arc::new(Condvar::new());
 
-        let trigger_worker = trigger.clone();
-        thread::spawn(move || {
-            ScheduleWorker::new(trigger_worker, schedule_rx, tx, operations_buffer).run();
-        });
+        let mut builder = thread::Builder::new();
+        if let Some(name) = thread_name {
+            builder = builder.name(name.to_owned());
+        }
+
+        // the worker stops as soon as the `WatchTimer` is dropped
+        try!(builder.spawn(move || {
+                ScheduleWorker::new(schedule_rx, tx, operations_buffer).run();
+            })
+            .map_err(Error::Io));
 
-        WatchTimer {
+        Ok(WatchTimer {
             counter: 0,
             schedule_tx: schedule_tx,
-            trigger: trigger,
             delay: delay,
-        }
+        })
     }
 
     pub fn schedule(&mut self, path: PathBuf) -> u64 {
@@ -194,8 +224,6 @@ impl WatchTimer {
             }))
             .expect("Failed to send a request to the global scheduling worker");
 
-        self.trigger.notify_one();
-
         self.counter
     }
 
@@ -204,4 +232,13 @@ impl WatchTimer {
             .send(Action::Ignore(id))
             .expect("Failed to send a request to the global scheduling worker");
     }
+
+    /// Fire all scheduled events immediately, returns once they have been delivered
+    pub fn flush(&self) {
+        let (tx, rx) = mpsc::channel();
+        self.schedule_tx
+            .send(Action::Flush(tx))
+            .expect("Failed to send a request to the global scheduling worker");
+        let _ = rx.recv();
+    }
 }
@@ -0,0 +1,248 @@
+//! Contains the `EventKind` type, a structured description of the action of a raw event.
+//!
+//! `Op` flags only tell which kind of action took place. `EventKind` carries the details a
+//! backend knows on top of that, like whether a file or a directory has been created, or whether
+//! the data or the meta data of a file has been modified. Backends fill in the kind as precisely
+//! as they can and use the `Any` variants for the details they don't know.
+//!
+//! Every kind converts to the `Op` of its event:
+//!
+//! ```
+//! use notify::Op;
+//! use notify::op;
+//! use notify::event::{EventKind, ModifyKind, MetadataKind};
+//!
+//! let kind = EventKind::Modify(ModifyKind::Metadata(MetadataKind::Permissions));
+//! assert_eq!(Op::from(kind), op::CHMOD);
+//! ```
+//!
+//! # Platform support
+//!
+//! __Linux__
+//!
+//! Created and removed entries are reported as `File` or `Dir`, except for the watched path itself
+//! and entries that have been moved out of the watched directory. Writes are reported as
+//! `Modify(Data)`, attribute changes as `Modify(Metadata(Any))` because inotify doesn't tell which
+//! attribute changed. Reads are reported as `Access(Read)`, if access events have been enabled.
+//! `WatchRemoved` and `Unmounted` are only reported on Linux.
+//!
+//! __OS X__
+//!
+//! FSEvents coalesces actions, so events with more than one `Op` flag have the kind `Any`.
+//!
+//! __Windows__
+//!
+//! Windows doesn't distinguish files from directories, or data from meta data changes.
+//!
+//! __Polling__
+//!
+//! Created entries are reported as `File` or `Dir` and modifications as `Modify(Data)`. Removed
+//! entries are reported as `Remove(Any)`.
+
+use super::op::{self, Op};
+
+/// Kind of the action of an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EventKind {
+    /// The action is unknown, or the event describes more than one action
+    ///
+    /// Converts to the empty `Op`, look at the `op` of the event instead.
+    Any,
+
+    /// A file or directory has been accessed
+    Access(AccessKind),
+
+    /// A file or directory has been created
+    Create(CreateKind),
+
+    /// The data or the meta data of a file or directory has been modified
+    Modify(ModifyKind),
+
+    /// A file or directory has been renamed
+    Rename(RenameMode),
+
+    /// A file or directory has been removed
+    Remove(RemoveKind),
+
+    /// The watched directories need to be rescanned
+    Rescan,
+
+    /// The watch of a watched path has been removed by the system, its changes are no longer
+    /// reported
+    WatchRemoved,
+
+    /// The file system containing a watched path has been unmounted
+    Unmounted,
+}
+
+/// Kind of an `EventKind::Access`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AccessKind {
+    /// The kind of access is unknown
+    Any,
+
+    /// A file has been read
+    Read,
+
+    /// A file has been opened
+    Open,
+
+    /// A file has been closed
+    Close(AccessMode),
+}
+
+/// Mode a file was opened with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AccessMode {
+    /// The mode is unknown
+    Any,
+
+    /// Opened for reading only
+    Read,
+
+    /// Opened for writing
+    Write,
+}
+
+/// Kind of an `EventKind::Create`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CreateKind {
+    /// The type of the created entry is unknown
+    Any,
+
+    /// A file has been created
+    File,
+
+    /// A directory has been created
+    Dir,
+}
+
+/// Kind of an `EventKind::Modify`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ModifyKind {
+    /// It's unknown what has been modified
+    Any,
+
+    /// The contents have been written to
+    Data,
+
+    /// The meta data has been changed
+    Metadata(MetadataKind),
+}
+
+/// Kind of a `ModifyKind::Metadata`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MetadataKind {
+    /// It's unknown which meta data has been changed
+    Any,
+
+    /// The permissions have been changed
+    Permissions,
+
+    /// The owner or the group has been changed
+    Ownership,
+
+    /// Extended attributes have been changed
+    Xattr,
+
+    /// The access or modification times have been changed
+    Times,
+}
+
+/// Mode of an `EventKind::Rename`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RenameMode {
+    /// It's unknown whether the path is the source or the destination
+    Any,
+
+    /// The path is the source of the rename
+    From,
+
+    /// The path is the destination of the rename
+    To,
+
+    /// The event describes both paths of the rename
+    Both,
+}
+
+/// Kind of an `EventKind::Remove`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RemoveKind {
+    /// The type of the removed entry is unknown
+    Any,
+
+    /// A file has been removed
+    File,
+
+    /// A directory has been removed
+    Dir,
+}
+
+impl EventKind {
+    /// Kind of a creation, knowing whether the entry is a directory
+    pub fn create(is_dir: bool) -> EventKind {
+        EventKind::Create(if is_dir {
+            CreateKind::Dir
+        } else {
+            CreateKind::File
+        })
+    }
+
+    /// Kind of a removal, knowing whether the entry is a directory
+    pub fn remove(is_dir: bool) -> EventKind {
+        EventKind::Remove(if is_dir {
+            RemoveKind::Dir
+        } else {
+            RemoveKind::File
+        })
+    }
+
+    /// Least precise kind of an `Op` with a single flag, `Any` otherwise
+    pub fn from_op(op: Op) -> EventKind {
+        match op {
+            op::CHMOD => EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)),
+            op::CREATE => EventKind::Create(CreateKind::Any),
+            op::REMOVE => EventKind::Remove(RemoveKind::Any),
+            op::RENAME => EventKind::Rename(RenameMode::Any),
+            op::WRITE => EventKind::Modify(ModifyKind::Any),
+            op::CLOSE_WRITE => EventKind::Access(AccessKind::Close(AccessMode::Write)),
+            op::RESCAN => EventKind::Rescan,
+            op::ACCESS => EventKind::Access(AccessKind::Read),
+            op::OPEN => EventKind::Access(AccessKind::Open),
+            op::CLOSE_NOWRITE => EventKind::Access(AccessKind::Close(AccessMode::Read)),
+            op::WATCH_REMOVED => EventKind::WatchRemoved,
+            op::UNMOUNTED => EventKind::Unmounted,
+            _ => EventKind::Any,
+        }
+    }
+}
+
+impl From<EventKind> for Op {
+    fn from(kind: EventKind) -> Op {
+        match kind {
+            EventKind::Any |
+            EventKind::Access(AccessKind::Any) |
+            EventKind::Access(AccessKind::Close(AccessMode::Any)) => Op::empty(),
+            EventKind::Access(AccessKind::Read) => op::ACCESS,
+            EventKind::Access(AccessKind::Open) => op::OPEN,
+            EventKind::Access(AccessKind::Close(AccessMode::Read)) => op::CLOSE_NOWRITE,
+            EventKind::Access(AccessKind::Close(AccessMode::Write)) => op::CLOSE_WRITE,
+            EventKind::Create(_) => op::CREATE,
+            EventKind::Modify(ModifyKind::Metadata(_)) => op::CHMOD,
+            EventKind::Modify(_) => op::WRITE,
+            EventKind::Rename(_) => op::RENAME,
+            EventKind::Remove(_) => op::REMOVE,
+            EventKind::Rescan => op::RESCAN,
+            EventKind::WatchRemoved => op::WATCH_REMOVED,
+            EventKind::Unmounted => op::UNMOUNTED,
+        }
+    }
+}
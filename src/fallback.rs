@@ -0,0 +1,450 @@
+//! Watcher implementation selecting the backend at runtime
+//!
+//! `FallbackWatcher` uses the recommended backend of the platform and falls back to polling if
+//! the native backend can't be initialized (e.g. because inotify's `max_user_instances` is
+//! exhausted) or runs out of resources while adding a watch (e.g. because inotify's
+//! `max_user_watches` is exhausted). Other errors of a watch are returned as they are. Watches that
+//! were added before falling back are moved to the `PollWatcher`.
+//!
+//! The backend can be forced with the `NOTIFY_BACKEND` environment variable. Valid values are
+//! `poll`, `native` or the name of the native backend of the platform (`inotify`, `fsevent` or
+//! `windows`). A forced backend never falls back.
+//!
+//! The `Config` is passed to both backends, except for the poll interval which only applies to
+//! the `PollWatcher` and the rename timeout and access events which only apply to the native
+//! backend. If the native backend doesn't support one of the options, the `FallbackWatcher` falls
+//! back to polling as well.
+
+use libc;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use super::{Config, Error, Filter, RawEvent, DebouncedEvent, EventHandler, Result, Watcher, RecursiveMode,
+            RecommendedWatcher, PollWatcher, WatchId, WatchInfo};
+use super::config;
+use super::watch_id::{self, WatchRegistry};
+
+/// Name of the environment variable used to force a backend
+pub const BACKEND_ENV_VAR: &'static str = "NOTIFY_BACKEND";
+
+/// Backend used by a `FallbackWatcher`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Linux inotify
+    INotify,
+    /// macOS FSEvents
+    FsEvent,
+    /// Windows `ReadDirectoryChangesW`
+    ReadDirectoryChanges,
+    /// Polling
+    Poll,
+}
+
+impl Backend {
+    /// The native backend of the current platform
+    #[cfg(target_os = "linux")]
+    pub fn native() -> Backend {
+        Backend::INotify
+    }
+
+    /// The native backend of the current platform
+    #[cfg(target_os = "macos")]
+    pub fn native() -> Backend {
+        Backend::FsEvent
+    }
+
+    /// The native backend of the current platform
+    #[cfg(target_os = "windows")]
+    pub fn native() -> Backend {
+        Backend::ReadDirectoryChanges
+    }
+
+    /// The native backend of the current platform
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    pub fn native() -> Backend {
+        Backend::Poll
+    }
+
+    /// Read the backend forced by the `NOTIFY_BACKEND` environment variable
+    ///
+    /// Returns an error if the variable names an unknown backend or one that isn't available on
+    /// the current platform.
+    pub fn from_env() -> Result<Option<Backend>> {
+        let name = match env::var(BACKEND_ENV_VAR) {
+            Ok(name) => name,
+            Err(_) => return Ok(None),
+        };
+
+        let backend = match name.trim().to_lowercase().as_ref() {
+            "" => return Ok(None),
+            "native" => Backend::native(),
+            "poll" => Backend::Poll,
+            "inotify" => Backend::INotify,
+            "fsevent" => Backend::FsEvent,
+            "windows" => Backend::ReadDirectoryChanges,
+            _ => {
+                return Err(Error::Generic(format!("{} names an unknown backend: {}",
+                                                  BACKEND_ENV_VAR,
+                                                  name)))
+            }
+        };
+
+        if backend != Backend::Poll && backend != Backend::native() {
+            return Err(Error::Generic(format!("{} names a backend that isn't available on this \
+                                               platform: {}",
+                                              BACKEND_ENV_VAR,
+                                              name)));
+        }
+
+        Ok(Some(backend))
+    }
+}
+
+/// Handler shared between the native watcher and the fallback watcher
+struct SharedHandler<H>(Arc<Mutex<H>>);
+
+impl<H> Clone for SharedHandler<H> {
+    fn clone(&self) -> SharedHandler<H> {
+        SharedHandler(self.0.clone())
+    }
+}
+
+impl<E, H: EventHandler<E>> EventHandler<E> for SharedHandler<H> {
+    fn handle_event(&mut self, event: E) {
+        if let Ok(mut handler) = self.0.lock() {
+            handler.handle_event(event);
+        }
+    }
+
+    fn handle_events(&mut self, events: Vec<E>) {
+        if let Ok(mut handler) = self.0.lock() {
+            handler.handle_events(events);
+        }
+    }
+}
+
+enum Inner<N> {
+    Native(N),
+    Poll(PollWatcher),
+}
+
+/// Handle id and watched path, with the handle of its watch in the inner watcher
+type Watch = (u64, PathBuf, RecursiveMode, Filter, WatchId);
+
+/// `Watcher` implementation using the native backend with a fallback to polling
+///
+/// The native watcher is the `RecommendedWatcher` of the platform unless given otherwise.
+pub struct FallbackWatcher<N = RecommendedWatcher> {
+    inner: Inner<N>,
+    fallback: Option<Box<Fn() -> Result<PollWatcher> + Send + Sync>>,
+    watches: Arc<Mutex<Vec<Watch>>>,
+    registry: WatchRegistry,
+    paused: bool,
+}
+
+impl<N: Watcher> FallbackWatcher<N> {
+    fn create<C, F>(native: C, fallback: F) -> Result<FallbackWatcher<N>>
+        where C: FnOnce() -> Result<N>,
+              F: Fn() -> Result<PollWatcher> + Send + Sync + 'static
+    {
+        let (inner, fallback) = match try!(Backend::from_env()) {
+            Some(Backend::Poll) => (Inner::Poll(try!(fallback())), None),
+            Some(_) => (Inner::Native(try!(native())), None),
+            None => {
+                match native() {
+                    Ok(watcher) => {
+                        let fallback: Box<Fn() -> Result<PollWatcher> + Send + Sync> =
+                            Box::new(fallback);
+                        (Inner::Native(watcher), Some(fallback))
+                    }
+                    Err(_) => (Inner::Poll(try!(fallback())), None),
+                }
+            }
+        };
+
+        let watches = Arc::new(Mutex::new(Vec::new()));
+        let registry = {
+            let watches = watches.clone();
+            WatchRegistry::new(move |_, id| {
+                // dropping the handle of the inner watcher removes its watch
+                if let Ok(mut watches) = watches.lock() {
+                    watches.retain(|&(handle_id, _, _, _, _)| handle_id != id);
+                }
+            })
+        };
+
+        Ok(FallbackWatcher {
+            inner: inner,
+            fallback: fallback,
+            watches: watches,
+            registry: registry,
+            paused: false,
+        })
+    }
+
+    /// The backend that is currently in use
+    pub fn backend(&self) -> Backend {
+        match self.inner {
+            Inner::Native(_) => Backend::native(),
+            Inner::Poll(_) => Backend::Poll,
+        }
+    }
+
+    /// Replace the native watcher with a `PollWatcher` and move all watches to it.
+    ///
+    /// If one of the watches can't be moved, the native watcher is kept with all its watches.
+    fn fall_back(&mut self) -> Result<()> {
+        let mut poll_watcher = match self.fallback {
+            Some(ref fallback) => try!(fallback()),
+            None => return Err(Error::Generic("no fallback available".to_owned())),
+        };
+        if self.paused {
+            try!(poll_watcher.pause());
+        }
+
+        if let Ok(mut watches) = self.watches.lock() {
+            let mut ids = Vec::with_capacity(watches.len());
+            for &(_, ref path, recursive_mode, ref filter, _) in watches.iter() {
+                ids.push(try!(poll_watcher.watch_filtered(path, recursive_mode, filter.clone())));
+            }
+            for (watch, id) in watches.iter_mut().zip(ids) {
+                // replacing the handle removes the watch from the native watcher
+                watch.4 = id;
+            }
+        }
+
+        self.fallback = None;
+        self.inner = Inner::Poll(poll_watcher);
+        Ok(())
+    }
+
+    fn watch_inner(&mut self,
+                   path: &Path,
+                   recursive_mode: RecursiveMode,
+                   filter: Filter)
+                   -> Result<WatchId> {
+        match self.inner {
+            Inner::Native(ref mut watcher) => watcher.watch_filtered(path, recursive_mode, filter),
+            Inner::Poll(ref mut watcher) => watcher.watch_filtered(path, recursive_mode, filter),
+        }
+    }
+}
+
+fn remove_path(watches: &mut Vec<Watch>, path: &Path) {
+    watches.retain(|&(_, ref p, _, _, _)| p != path);
+}
+
+/// Whether a watch failed because the native backend ran out of resources
+fn is_exhausted(err: &Error) -> bool {
+    match *err {
+        Error::MaxFilesWatch(_) => true,
+        Error::Io(ref err) => {
+            match err.raw_os_error() {
+                Some(errno) => errno == libc::ENOSPC || errno == libc::EMFILE,
+                None => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+impl<N: Watcher> Watcher for FallbackWatcher<N> {
+    fn new_raw_with_config<H: EventHandler<RawEvent>>(handler: H,
+                                                      config: Config)
+                                                      -> Result<FallbackWatcher<N>> {
+        let handler = SharedHandler(Arc::new(Mutex::new(handler)));
+        let poll_handler = handler.clone();
+        let native_config = config::without_poll_options(&config);
+        let poll_config = config::without_native_options(&config);
+        FallbackWatcher::create(move || Watcher::new_raw_with_config(handler, native_config),
+                                move || {
+                                    Watcher::new_raw_with_config(poll_handler.clone(),
+                                                                 poll_config.clone())
+                                })
+    }
+
+    fn new_with_config<H: EventHandler<DebouncedEvent>>(handler: H,
+                                                        config: Config)
+                                                        -> Result<FallbackWatcher<N>> {
+        let handler = SharedHandler(Arc::new(Mutex::new(handler)));
+        let poll_handler = handler.clone();
+        let native_config = config::without_poll_options(&config);
+        let poll_config = config::without_native_options(&config);
+        FallbackWatcher::create(move || Watcher::new_with_config(handler, native_config),
+                                move || {
+                                    Watcher::new_with_config(poll_handler.clone(),
+                                                             poll_config.clone())
+                                })
+    }
+
+    fn watch_filtered<P: AsRef<Path>>(&mut self,
+                                      path: P,
+                                      recursive_mode: RecursiveMode,
+                                      filter: Filter)
+                                      -> Result<WatchId> {
+        let id = match self.watch_inner(path.as_ref(), recursive_mode, filter.clone()) {
+            // only a backend out of resources is a reason to fall back
+            Err(ref e) if self.fallback.is_some() && is_exhausted(e) => {
+                try!(self.fall_back());
+                try!(self.watch_inner(path.as_ref(), recursive_mode, filter.clone()))
+            }
+            Err(e) => return Err(e),
+            Ok(id) => id,
+        };
+
+        let handle = self.registry.register(path.as_ref().to_owned());
+        if let Ok(mut watches) = self.watches.lock() {
+            watches.push((watch_id::id(&handle),
+                          path.as_ref().to_owned(),
+                          recursive_mode,
+                          filter,
+                          id));
+        }
+        Ok(handle)
+    }
+
+    fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let result = match self.inner {
+            Inner::Native(ref mut watcher) => watcher.unwatch(path.as_ref()),
+            Inner::Poll(ref mut watcher) => watcher.unwatch(path.as_ref()),
+        };
+
+        // the handles of the inner watcher have no effect anymore
+        if let Ok(mut watches) = self.watches.lock() {
+            remove_path(&mut watches, path.as_ref());
+        }
+        self.registry.forget(path.as_ref());
+        result
+    }
+
+    fn watch_info(&self) -> Result<Vec<WatchInfo>> {
+        match self.inner {
+            Inner::Native(ref watcher) => watcher.watch_info(),
+            Inner::Poll(ref watcher) => watcher.watch_info(),
+        }
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        try!(match self.inner {
+            Inner::Native(ref mut watcher) => watcher.pause(),
+            Inner::Poll(ref mut watcher) => watcher.pause(),
+        });
+        self.paused = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        try!(match self.inner {
+            Inner::Native(ref mut watcher) => watcher.resume(),
+            Inner::Poll(ref mut watcher) => watcher.resume(),
+        });
+        self.paused = false;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self.inner {
+            Inner::Native(ref mut watcher) => watcher.flush(),
+            Inner::Poll(ref mut watcher) => watcher.flush(),
+        }
+    }
+}
+
+/// Native watcher failing every watch with the error returned by the function
+#[cfg(test)]
+struct FailingWatcher(fn(&Path) -> Error);
+
+#[cfg(test)]
+impl Watcher for FailingWatcher {
+    fn new_raw_with_config<H: EventHandler<RawEvent>>(_handler: H,
+                                                      _config: Config)
+                                                      -> Result<FailingWatcher> {
+        Err(Error::Generic("a failing watcher has to be created with its error".to_owned()))
+    }
+
+    fn new_with_config<H: EventHandler<DebouncedEvent>>(_handler: H,
+                                                        _config: Config)
+                                                        -> Result<FailingWatcher> {
+        Err(Error::Generic("a failing watcher has to be created with its error".to_owned()))
+    }
+
+    fn watch_filtered<P: AsRef<Path>>(&mut self,
+                                      path: P,
+                                      _recursive_mode: RecursiveMode,
+                                      _filter: Filter)
+                                      -> Result<WatchId> {
+        Err((self.0)(path.as_ref()))
+    }
+
+    fn unwatch<P: AsRef<Path>>(&mut self, _path: P) -> Result<()> {
+        Err(Error::WatchNotFound)
+    }
+
+    fn watch_info(&self) -> Result<Vec<WatchInfo>> {
+        Ok(Vec::new())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn fall_back_on_exhausted_resources() {
+    use std::io;
+
+    fn max_files_watch(path: &Path) -> Error {
+        Error::MaxFilesWatch(path.to_owned())
+    }
+    fn no_space(_: &Path) -> Error {
+        Error::Io(io::Error::from_raw_os_error(libc::ENOSPC))
+    }
+    fn too_many_files(_: &Path) -> Error {
+        Error::Io(io::Error::from_raw_os_error(libc::EMFILE))
+    }
+
+    for error in &[max_files_watch as fn(&Path) -> Error, no_space, too_many_files] {
+        let error = *error;
+        let mut watcher = FallbackWatcher::create(move || Ok(FailingWatcher(error)),
+                                                  || PollWatcher::new_raw(|_: RawEvent| ()))
+            .expect("failed to create fallback watcher");
+        let _watch = watcher.watch(env::temp_dir(), RecursiveMode::NonRecursive)
+            .expect("failed to fall back");
+        assert_eq!(watcher.backend(), Backend::Poll);
+    }
+}
+
+#[test]
+fn keep_native_on_other_errors() {
+    use std::io;
+
+    fn permission_denied(path: &Path) -> Error {
+        Error::PermissionDenied(path.to_owned())
+    }
+    fn not_a_directory(path: &Path) -> Error {
+        Error::NotADirectory(path.to_owned())
+    }
+    fn io_error(_: &Path) -> Error {
+        Error::Io(io::Error::from_raw_os_error(libc::EIO))
+    }
+
+    for error in &[permission_denied as fn(&Path) -> Error, not_a_directory, io_error] {
+        let error = *error;
+        let mut watcher = FallbackWatcher::create(move || Ok(FailingWatcher(error)),
+                                                  || PollWatcher::new_raw(|_: RawEvent| ()))
+            .expect("failed to create fallback watcher");
+        match watcher.watch(env::temp_dir(), RecursiveMode::NonRecursive) {
+            Err(ref e) => assert_eq!(e.to_string(), error(&env::temp_dir()).to_string()),
+            Ok(_) => panic!("the error of the native watcher has been hidden"),
+        }
+        assert_eq!(watcher.backend(), Backend::native());
+    }
+}
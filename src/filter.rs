@@ -0,0 +1,496 @@
+use glob::{MatchOptions, Pattern};
+#[cfg(feature = "ignore")]
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+#[cfg(feature = "ignore")]
+use std::cell::RefCell;
+use std::collections::HashMap;
+#[cfg(feature = "ignore")]
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "ignore")]
+use std::time::SystemTime;
+use super::{Error, Result};
+
+/// Names of the ignore files read in every directory, in order of increasing precedence
+#[cfg(feature = "ignore")]
+const IGNORE_FILES: &'static [&'static str] = &[".gitignore", ".ignore"];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Include and exclude patterns of a watch
+///
+/// Passed to [`Watcher::watch_filtered`](trait.Watcher.html#tymethod.watch_filtered). Patterns
+/// use the syntax of the [glob](https://docs.rs/glob) crate and are matched against the path of
+/// an event relative to the watched path, so `*.rs` only matches files directly inside the
+/// watched directory while `**/*.rs` matches files at any depth.
+///
+/// Exclude patterns are applied to the path and all of its parents, excluding `target` excludes
+/// the whole `target` directory. Excluded directories are skipped when a backend recursively adds
+/// watches or scans for changes, so they don't use up any resources like inotify watch
+/// descriptors. Include patterns are applied to events only: if there are any include patterns,
+/// only events for paths matching at least one of them are delivered.
+///
+/// Filters are applied before events are debounced. The watched path itself is never filtered.
+///
+/// # Ignore files
+///
+/// Requires the `ignore` feature. With [`use_ignore_files`](#method.use_ignore_files) enabled, paths ignored by `.gitignore`
+/// and `.ignore` files or by the global git excludes (`core.excludesFile`) are treated like
+/// excluded paths. Ignore files in the parent directories of the watched path are respected up
+/// to the root of the git repository. Changes to the ignore files inside the watched directory
+/// and in its parent directories are picked up while the watcher is running. The global git
+/// excludes are read once when the path is watched.
+///
+/// # Example
+///
+/// ```no_run
+/// # use notify::{Filter, Watcher, RecursiveMode, raw_watcher};
+/// # use std::sync::mpsc::channel;
+/// # let (tx, rx) = channel();
+/// # let mut watcher = raw_watcher(tx).unwrap();
+/// let mut filter = Filter::new();
+/// filter.include("**/*.rs").unwrap();
+/// filter.include("Cargo.toml").unwrap();
+/// filter.exclude("target").unwrap();
+/// filter.exclude(".git").unwrap();
+///
+/// let _watch = watcher.watch_filtered("/home/test/project", RecursiveMode::Recursive, filter).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    ignore_files: bool,
+}
+
+impl Filter {
+    /// Create a filter accepting all paths
+    pub fn new() -> Filter {
+        Filter::default()
+    }
+
+    /// Add an include pattern.
+    ///
+    /// Returns an error if the pattern is invalid.
+    pub fn include(&mut self, pattern: &str) -> Result<()> {
+        let pattern = try!(parse_pattern(pattern));
+        self.include.push(pattern);
+        Ok(())
+    }
+
+    /// Add an exclude pattern.
+    ///
+    /// Returns an error if the pattern is invalid.
+    pub fn exclude(&mut self, pattern: &str) -> Result<()> {
+        let pattern = try!(parse_pattern(pattern));
+        self.exclude.push(pattern);
+        Ok(())
+    }
+
+    /// Treat paths ignored by `.gitignore`, `.ignore` and the global git excludes as excluded.
+    ///
+    /// Changes to the global git excludes aren't picked up until the path is watched again.
+    /// Requires the `ignore` feature. Disabled by default.
+    #[cfg(feature = "ignore")]
+    pub fn use_ignore_files(&mut self, enabled: bool) {
+        self.ignore_files = enabled;
+    }
+
+    /// Returns true if the filter accepts all paths
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty() && !self.ignore_files
+    }
+
+    /// Check whether a path relative to the watched path is excluded.
+    ///
+    /// A path is excluded if it or one of its parents matches an exclude pattern. Ignore files
+    /// are not taken into account, as they depend on the watched path.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        if self.exclude.is_empty() {
+            return false;
+        }
+
+        let mut prefix = PathBuf::new();
+        for component in path.components() {
+            prefix.push(component.as_os_str());
+            if self.exclude.iter().any(|p| p.matches_path_with(&prefix, MATCH_OPTIONS)) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Check whether events for a path relative to the watched path are delivered.
+    ///
+    /// Ignore files are not taken into account, as they depend on the watched path.
+    pub fn matches(&self, path: &Path) -> bool {
+        if path.as_os_str().is_empty() {
+            return true;
+        }
+
+        if self.is_excluded(path) {
+            return false;
+        }
+
+        self.include.is_empty() ||
+        self.include.iter().any(|p| p.matches_path_with(path, MATCH_OPTIONS))
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Result<Pattern> {
+    Pattern::new(pattern).map_err(|e| {
+        Error::Generic(format!("Invalid pattern {:?}: {}", pattern, e.msg))
+    })
+}
+
+/// Rules of the ignore files applying to a watched directory
+#[cfg(feature = "ignore")]
+struct IgnoreRules {
+    root: PathBuf,
+    /// Directories above `root` whose ignore files apply, innermost first
+    parent_dirs: Vec<PathBuf>,
+    /// Rules of `parent_dirs`, along with the state of their ignore files when they were loaded
+    parents: RefCell<(Vec<Option<(SystemTime, u64)>>, Vec<Gitignore>)>,
+    global: Gitignore,
+    /// Rules of the directories below `root` (and `root` itself), loaded when first needed
+    dirs: RefCell<HashMap<PathBuf, Option<Gitignore>>>,
+}
+
+#[cfg(feature = "ignore")]
+impl IgnoreRules {
+    fn new(root: &Path) -> IgnoreRules {
+        // respect the ignore files of the repository containing the watched directory, up to its
+        // root
+        let mut parent_dirs = Vec::new();
+        if !root.join(".git").exists() {
+            let mut dir = root.parent();
+            while let Some(d) = dir {
+                parent_dirs.push(d.to_owned());
+                if d.join(".git").exists() {
+                    break;
+                }
+                dir = d.parent();
+            }
+            if dir.is_none() {
+                // not inside a repository
+                parent_dirs.clear();
+            }
+        }
+
+        let parents = (ignore_files_state(&parent_dirs), load_all_ignore_files(&parent_dirs));
+        IgnoreRules {
+            root: root.to_owned(),
+            parent_dirs: parent_dirs,
+            parents: RefCell::new(parents),
+            global: Gitignore::global().0,
+            dirs: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Reload the rules of the parent directories if one of their ignore files has changed
+    ///
+    /// They aren't inside the watched directory, so their changes aren't reported as events.
+    fn reload_parents(&self) {
+        let state = ignore_files_state(&self.parent_dirs);
+        let mut parents = self.parents.borrow_mut();
+        if parents.0 != state {
+            *parents = (state, load_all_ignore_files(&self.parent_dirs));
+        }
+    }
+
+    /// Check whether `path` or one of its parents below the root is ignored
+    fn is_ignored(&self, path: &Path) -> bool {
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        self.reload_parents();
+
+        let mut current = self.root.clone();
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            current.push(component.as_os_str());
+            // the path itself may have been removed already, all of its parents are directories
+            let is_dir = components.peek().is_some() || current.is_dir();
+            if self.is_ignored_single(&current, is_dir) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn is_ignored_single(&self, path: &Path, is_dir: bool) -> bool {
+        let mut dirs = self.dirs.borrow_mut();
+
+        // rules of deeper directories take precedence
+        let mut dir = path.parent();
+        while let Some(d) = dir {
+            if !d.starts_with(&self.root) {
+                break;
+            }
+            let rules = dirs.entry(d.to_owned()).or_insert_with(|| load_ignore_files(d));
+            if let Some(ref rules) = *rules {
+                let m = rules.matched(path, is_dir);
+                if !m.is_none() {
+                    return m.is_ignore();
+                }
+            }
+            dir = d.parent();
+        }
+
+        let parents = self.parents.borrow();
+        for rules in parents.1.iter().chain(Some(&self.global)) {
+            let m = rules.matched(path, is_dir);
+            if !m.is_none() {
+                return m.is_ignore();
+            }
+        }
+        false
+    }
+
+    /// Reload the rules of `dir` the next time they're needed
+    fn invalidate(&self, dir: &Path) {
+        self.dirs.borrow_mut().remove(dir);
+    }
+}
+
+/// Load the rules of the ignore files in `dir`, if there are any
+#[cfg(feature = "ignore")]
+fn load_ignore_files(dir: &Path) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+    for name in IGNORE_FILES {
+        let file = dir.join(name);
+        if file.is_file() {
+            // invalid lines are skipped, the remaining rules still apply
+            let _ = builder.add(file);
+            found = true;
+        }
+    }
+
+    if found {
+        builder.build().ok()
+    } else {
+        None
+    }
+}
+
+/// Load the rules of the ignore files in each of `dirs`
+#[cfg(feature = "ignore")]
+fn load_all_ignore_files(dirs: &[PathBuf]) -> Vec<Gitignore> {
+    dirs.iter().filter_map(|dir| load_ignore_files(dir)).collect()
+}
+
+/// Modification time and size of the ignore files in each of `dirs`, `None` for missing files
+#[cfg(feature = "ignore")]
+fn ignore_files_state(dirs: &[PathBuf]) -> Vec<Option<(SystemTime, u64)>> {
+    dirs.iter()
+        .flat_map(|dir| IGNORE_FILES.iter().map(move |name| dir.join(name)))
+        .map(|file| {
+            fs::metadata(file)
+                .and_then(|metadata| metadata.modified().map(|time| (time, metadata.len())))
+                .ok()
+        })
+        .collect()
+}
+
+/// Returns true if `path` is an ignore file
+#[cfg(feature = "ignore")]
+pub fn is_ignore_file(path: &Path) -> bool {
+    match path.file_name() {
+        Some(name) => IGNORE_FILES.iter().any(|f| name == *f),
+        None => false,
+    }
+}
+
+/// Returns true if `path` is an ignore file, ignore files are only read with the `ignore` feature
+#[cfg(not(feature = "ignore"))]
+pub fn is_ignore_file(_path: &Path) -> bool {
+    false
+}
+
+struct WatchFilter {
+    filter: Filter,
+    is_recursive: bool,
+    #[cfg(feature = "ignore")]
+    ignore_rules: Option<IgnoreRules>,
+}
+
+impl WatchFilter {
+    #[cfg(feature = "ignore")]
+    fn new(root: &Path, filter: Filter, is_recursive: bool) -> WatchFilter {
+        let ignore_rules = if filter.ignore_files {
+            Some(IgnoreRules::new(root))
+        } else {
+            None
+        };
+        WatchFilter {
+            filter: filter,
+            is_recursive: is_recursive,
+            ignore_rules: ignore_rules,
+        }
+    }
+
+    #[cfg(not(feature = "ignore"))]
+    fn new(_root: &Path, filter: Filter, is_recursive: bool) -> WatchFilter {
+        WatchFilter {
+            filter: filter,
+            is_recursive: is_recursive,
+        }
+    }
+
+    /// Check whether a path relative to the root is inside the watch
+    fn covers(&self, relative: &Path) -> bool {
+        self.is_recursive || relative.components().count() <= 1
+    }
+
+    /// Check whether `path` or one of its parents is ignored by the ignore files
+    #[cfg(feature = "ignore")]
+    fn is_ignored(&self, path: &Path) -> bool {
+        self.ignore_rules.as_ref().map_or(false, |rules| rules.is_ignored(path))
+    }
+
+    #[cfg(not(feature = "ignore"))]
+    fn is_ignored(&self, _path: &Path) -> bool {
+        false
+    }
+}
+
+/// Filters of all watches of a watcher
+///
+/// Every handle of a watched path has its own filter. Events are matched against the filters of
+/// the innermost watched path containing their path and are delivered if any of its handles
+/// accepts them.
+#[derive(Default)]
+pub struct WatchFilters {
+    /// Filters of the handles of every watched path, by handle id
+    filters: HashMap<PathBuf, HashMap<u64, WatchFilter>>,
+}
+
+impl WatchFilters {
+    pub fn insert(&mut self, root: PathBuf, id: u64, filter: Filter, is_recursive: bool) {
+        let watch = WatchFilter::new(&root, filter, is_recursive);
+        self.filters.entry(root).or_insert_with(HashMap::new).insert(id, watch);
+    }
+
+    /// Remove the filters of all handles of a watched path
+    pub fn remove(&mut self, root: &Path) {
+        self.filters.remove(root);
+    }
+
+    /// Remove the filter of a handle, returns true if other handles of the path are left
+    pub fn remove_handle(&mut self, root: &Path, id: u64) -> bool {
+        let remaining = match self.filters.get_mut(root) {
+            Some(watches) => {
+                watches.remove(&id);
+                !watches.is_empty()
+            }
+            None => return false,
+        };
+        if !remaining {
+            self.filters.remove(root);
+        }
+        remaining
+    }
+
+    /// Move the filters of a renamed path and of the watched paths inside it to their new paths
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        let moved: Vec<PathBuf> =
+            self.filters.keys().filter(|root| root.starts_with(from)).cloned().collect();
+        for old in moved {
+            if let Some(watches) = self.filters.remove(&old) {
+                let root = match old.strip_prefix(from) {
+                    Ok(relative) if !relative.as_os_str().is_empty() => to.join(relative),
+                    _ => to.to_owned(),
+                };
+                // the ignore files are looked up relative to the new path
+                let watches = watches.into_iter()
+                    .map(|(id, watch)| {
+                        (id, WatchFilter::new(&root, watch.filter, watch.is_recursive))
+                    })
+                    .collect();
+                self.filters.insert(root, watches);
+            }
+        }
+    }
+
+    /// Whether any handle watches a path recursively
+    pub fn is_recursive(&self, root: &Path) -> bool {
+        self.filters.get(root).map_or(false, |watches| watches.values().any(|w| w.is_recursive))
+    }
+
+    /// Find the innermost watch containing `path`, returns its filters and the relative path
+    fn lookup<'a>(&self, path: &'a Path) -> Option<(&HashMap<u64, WatchFilter>, &'a Path)> {
+        let mut root = Some(path);
+        while let Some(r) = root {
+            if let Some(watches) = self.filters.get(r) {
+                if let Ok(relative) = path.strip_prefix(r) {
+                    return Some((watches, relative));
+                }
+            }
+            root = r.parent();
+        }
+        None
+    }
+
+    /// Find the innermost watch containing `path`, returns its root and the relative path
+    pub fn relative<'a>(&self, path: &'a Path) -> Option<(&'a Path, &'a Path)> {
+        let mut root = Some(path);
+        while let Some(r) = root {
+            if self.filters.contains_key(r) {
+                if let Ok(relative) = path.strip_prefix(r) {
+                    return Some((r, relative));
+                }
+            }
+            root = r.parent();
+        }
+        None
+    }
+
+    /// Check whether a path is excluded from recursive watches and scans
+    ///
+    /// A path is excluded if no handle of the watch includes it.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        match self.lookup(path) {
+            Some((watches, relative)) => {
+                watches.values().all(|watch| {
+                    !watch.covers(relative) || watch.filter.is_excluded(relative) ||
+                    watch.is_ignored(path)
+                })
+            }
+            None => false,
+        }
+    }
+
+    /// Check whether events for a path are delivered
+    pub fn matches(&self, path: &Path) -> bool {
+        match self.lookup(path) {
+            Some((watches, relative)) => {
+                watches.values().any(|watch| {
+                    watch.covers(relative) && watch.filter.matches(relative) &&
+                    (relative.as_os_str().is_empty() || !watch.is_ignored(path))
+                })
+            }
+            None => true,
+        }
+    }
+
+    /// Update the rules of all watches affected by a change to the ignore file at `path`
+    #[cfg(feature = "ignore")]
+    pub fn ignore_file_changed(&self, path: &Path) {
+        if let Some(dir) = path.parent() {
+            for watch in self.filters.values().flat_map(|watches| watches.values()) {
+                if let Some(ref rules) = watch.ignore_rules {
+                    rules.invalidate(dir);
+                }
+            }
+        }
+    }
+
+    #[cfg(not(feature = "ignore"))]
+    pub fn ignore_file_changed(&self, _path: &Path) {}
+}
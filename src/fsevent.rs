@@ -24,11 +24,17 @@ use std::path::{Path, PathBuf};
 use std::slice;
 use std::str::from_utf8;
 use std::sync::{Arc, Mutex};
-use std::sync::mpsc::{channel, Sender, Receiver};
+use std::sync::mpsc::{channel, Receiver};
 use std::thread;
-use std::time::Duration;
-use super::{Error, RawEvent, DebouncedEvent, op, Result, Watcher, RecursiveMode};
-use super::debounce::{Debounce, EventTx};
+use super::{Config, Error, Filter, RawEvent, DebouncedEvent, EventHandler, EventKind, op, Result,
+            Watcher, RecursiveMode, WatchId, WatchInfo, WatchHealth};
+use super::event::{CreateKind, MetadataKind, ModifyKind, RemoveKind};
+use super::config;
+use super::debounce::EventTx;
+use super::watch_id::{self, WatchRegistry};
+
+/// Options supported by the `FsEventWatcher`
+const SUPPORTED_OPTIONS: &'static [&'static str] = &["relative_paths", "thread_name"];
 
 /// FSEvents-based `Watcher` implementation
 pub struct FsEventWatcher {
@@ -38,8 +44,14 @@ pub struct FsEventWatcher {
     flags: fs::FSEventStreamCreateFlags,
     event_tx: Arc<Mutex<EventTx>>,
     runloop: Option<usize>,
+    /// The running stream, to flush it
+    stream: Option<usize>,
     context: Option<Box<StreamContextInfo>>,
     recursive_info: HashMap<PathBuf, bool>,
+    thread_name: Option<String>,
+    registry: WatchRegistry,
+    /// Paths and ids of the dropped handles, removed the next time the stream is restarted
+    released: Arc<Mutex<Vec<(PathBuf, u64)>>>,
 }
 
 // CFMutableArrayRef is a type alias to *mut libc::c_void, so FsEventWatcher is not Send/Sync
@@ -69,16 +81,41 @@ fn translate_flags(flags: fse::StreamFlags) -> op::Op {
     ret
 }
 
-fn send_pending_rename_event(event: Option<RawEvent>, event_tx: &mut EventTx) {
+/// Detailed kind of an event, `Any` if FSEvents coalesced several actions
+fn translate_kind(flags: fse::StreamFlags) -> EventKind {
+    match translate_flags(flags) {
+        op::CREATE if flags.contains(fse::IS_DIR) => EventKind::Create(CreateKind::Dir),
+        op::CREATE if flags.contains(fse::IS_FILE) => EventKind::Create(CreateKind::File),
+        op::REMOVE if flags.contains(fse::IS_DIR) => EventKind::Remove(RemoveKind::Dir),
+        op::REMOVE if flags.contains(fse::IS_FILE) => EventKind::Remove(RemoveKind::File),
+        op::CHMOD if !flags.contains(fse::ITEM_XATTR_MOD) => {
+            EventKind::Modify(ModifyKind::Metadata(MetadataKind::Ownership))
+        }
+        op::CHMOD if !flags.contains(fse::ITEM_CHANGE_OWNER) => {
+            EventKind::Modify(ModifyKind::Metadata(MetadataKind::Xattr))
+        }
+        op::WRITE => EventKind::Modify(ModifyKind::Data),
+        o => EventKind::from_op(o),
+    }
+}
+
+fn send_pending_rename_event(event: Option<RawEvent>, batch: &mut Vec<RawEvent>) {
     if let Some(e) = event {
-        event_tx.send(RawEvent {
+        batch.push(RawEvent {
             path: e.path,
             op: e.op,
+            kind: e.kind,
             cookie: None,
+            relative: None,
         });
     }
 }
 
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    fn FSEventStreamFlushSync(stream_ref: fs::FSEventStreamRef);
+}
+
 struct StreamContextInfo {
     event_tx: Arc<Mutex<EventTx>>,
     done: Receiver<()>,
@@ -104,6 +141,7 @@ impl FsEventWatcher {
         }
 
         self.runloop = None;
+        self.stream = None;
         if let Some(ref context_info) = self.context {
             // sync done channel
             match context_info.done.recv() {
@@ -115,6 +153,40 @@ impl FsEventWatcher {
         self.context = None;
     }
 
+    /// Remove the handles that have been dropped, the stream has to be stopped
+    fn remove_released(&mut self) {
+        let released: Vec<(PathBuf, u64)> = match self.released.lock() {
+            Ok(mut released) => released.drain(..).collect(),
+            Err(_) => return,
+        };
+        for (path, id) in released {
+            self.remove_handle(path, id);
+        }
+    }
+
+    /// Remove a handle of a watched path, the path is removed along with its last handle
+    fn remove_handle(&mut self, path: PathBuf, id: u64) {
+        let p = path.canonicalize().unwrap_or_else(|_| path.clone());
+        let is_recursive = match self.event_tx.lock() {
+            Ok(mut event_tx) => {
+                if event_tx.filters_mut().remove_handle(&p, id) {
+                    Some(event_tx.filters().is_recursive(&p))
+                } else {
+                    None
+                }
+            }
+            Err(_) => return,
+        };
+        match is_recursive {
+            Some(is_recursive) => {
+                self.recursive_info.insert(p, is_recursive);
+            }
+            None => {
+                let _ = self.remove_path(path);
+            }
+        }
+    }
+
     fn remove_path<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         let str_path = path.as_ref().to_str().unwrap();
         unsafe {
@@ -134,26 +206,44 @@ impl FsEventWatcher {
             path.as_ref().to_owned()
         };
         match self.recursive_info.remove(&p) {
-            Some(_) => Ok(()),
+            Some(_) => {
+                if let Ok(mut event_tx) = self.event_tx.lock() {
+                    event_tx.filters_mut().remove(&p);
+                }
+                Ok(())
+            }
             None => Err(Error::WatchNotFound),
         }
     }
 
     // https://github.com/thibaudgg/rb-fsevent/blob/master/ext/fsevent_watch/main.c
-    fn append_path<P: AsRef<Path>>(&mut self, path: P, recursive_mode: RecursiveMode) {
-        let str_path = path.as_ref().to_str().unwrap();
-        unsafe {
-            let cf_path = cf::str_path_to_cfstring_ref(str_path);
-            cf::CFArrayAppendValue(self.paths, cf_path);
-            cf::CFRelease(cf_path);
+    fn append_path<P: AsRef<Path>>(&mut self,
+                                   path: P,
+                                   id: u64,
+                                   recursive_mode: RecursiveMode,
+                                   filter: Filter) {
+        let canonicalized_path = path.as_ref().to_path_buf().canonicalize().unwrap();
+        // a path watched several times is only passed to the stream once
+        if !self.recursive_info.contains_key(&canonicalized_path) {
+            let str_path = path.as_ref().to_str().unwrap();
+            unsafe {
+                let cf_path = cf::str_path_to_cfstring_ref(str_path);
+                cf::CFArrayAppendValue(self.paths, cf_path);
+                cf::CFRelease(cf_path);
+            }
         }
-        self.recursive_info.insert(path.as_ref().to_path_buf().canonicalize().unwrap(),
-                                   recursive_mode.is_recursive());
+        // event paths are canonicalized as well
+        let mut is_recursive = recursive_mode.is_recursive();
+        if let Ok(mut event_tx) = self.event_tx.lock() {
+            event_tx.filters_mut().insert(canonicalized_path.clone(), id, filter, is_recursive);
+            is_recursive = event_tx.filters().is_recursive(&canonicalized_path);
+        }
+        self.recursive_info.insert(canonicalized_path, is_recursive);
     }
 
     fn run(&mut self) -> Result<()> {
         if unsafe { cf::CFArrayGetCount(self.paths) } == 0 {
-            return Err(Error::PathNotFound);
+            return Err(Error::Generic("No paths to watch.".to_owned()));
         }
 
         // done channel is used to sync quit status of runloop thread
@@ -190,7 +280,12 @@ impl FsEventWatcher {
         // channel to pass runloop around
         let (rl_tx, rl_rx) = channel();
 
-        thread::spawn(move || {
+        let mut builder = thread::Builder::new();
+        if let Some(ref name) = self.thread_name {
+            builder = builder.name(name.clone());
+        }
+
+        try!(builder.spawn(move || {
             let stream = dummy as *mut libc::c_void;
             unsafe {
                 let cur_runloop = cf::CFRunLoopGetCurrent();
@@ -209,9 +304,10 @@ impl FsEventWatcher {
                 fs::FSEventStreamRelease(stream);
             }
             done_tx.send(()).expect("error while signal run loop is done");
-        });
+        }).map_err(Error::Io));
         // block until runloop has been set
         self.runloop = Some(rl_rx.recv().unwrap());
+        self.stream = Some(dummy);
 
         Ok(())
     }
@@ -237,6 +333,8 @@ pub unsafe extern "C" fn callback(
     let ids = slice::from_raw_parts_mut(i_ptr, num);
 
     if let Ok(mut event_tx) = (*info).event_tx.lock() {
+        // the events of one callback are delivered as one batch
+        let mut batch = Vec::new();
         let mut rename_event: Option<RawEvent> = None;
 
         for p in 0..num {
@@ -263,10 +361,12 @@ pub unsafe extern "C" fn callback(
             }
 
             if flag.contains(fse::MUST_SCAN_SUBDIRS) {
-                event_tx.send(RawEvent {
+                batch.push(RawEvent {
                     path: None,
                     op: Ok(op::RESCAN),
+                    kind: EventKind::Rescan,
                     cookie: None,
+                    relative: None,
                 });
             }
 
@@ -274,93 +374,182 @@ pub unsafe extern "C" fn callback(
                 if flag.contains(fse::ITEM_RENAMED) {
                     if let Some(e) = rename_event {
                         if e.cookie == Some((id - 1) as u32) {
-                            event_tx.send(e);
-                            event_tx.send(RawEvent {
+                            batch.push(e);
+                            batch.push(RawEvent {
                                 op: Ok(translate_flags(flag)),
+                                kind: translate_kind(flag),
                                 path: Some(path),
                                 cookie: Some((id - 1) as u32),
+                                relative: None,
                             });
                             rename_event = None;
                         } else {
-                            send_pending_rename_event(Some(e), &mut event_tx);
+                            send_pending_rename_event(Some(e), &mut batch);
                             rename_event = Some(RawEvent {
                                 path: Some(path),
                                 op: Ok(translate_flags(flag)),
+                                kind: translate_kind(flag),
                                 cookie: Some(id as u32),
+                                relative: None,
                             });
                         }
                     } else {
                         rename_event = Some(RawEvent {
                             path: Some(path),
                             op: Ok(translate_flags(flag)),
+                            kind: translate_kind(flag),
                             cookie: Some(id as u32),
+                            relative: None,
                         });
                     }
                 } else {
-                    send_pending_rename_event(rename_event, &mut event_tx);
+                    send_pending_rename_event(rename_event, &mut batch);
                     rename_event = None;
 
-                    event_tx.send(RawEvent {
+                    batch.push(RawEvent {
                         op: Ok(translate_flags(flag)),
+                        kind: translate_kind(flag),
                         path: Some(path),
                         cookie: None,
+                        relative: None,
                     });
                 }
             }
         }
 
-        send_pending_rename_event(rename_event, &mut event_tx);
+        send_pending_rename_event(rename_event, &mut batch);
+        event_tx.send_all(batch);
     }
 }
 
 
-impl Watcher for FsEventWatcher {
-    fn new_raw(tx: Sender<RawEvent>) -> Result<FsEventWatcher> {
-        Ok(FsEventWatcher {
+impl FsEventWatcher {
+    fn create(event_tx: EventTx, config: &Config) -> FsEventWatcher {
+        let released = Arc::new(Mutex::new(Vec::new()));
+        let registry = {
+            let released = released.clone();
+            WatchRegistry::new(move |path, id| {
+                // the stream can only be changed from the watcher
+                if let Ok(mut released) = released.lock() {
+                    released.push((path.to_owned(), id));
+                }
+            })
+        };
+
+        FsEventWatcher {
             paths: unsafe {
                 cf::CFArrayCreateMutable(cf::kCFAllocatorDefault, 0, &cf::kCFTypeArrayCallBacks)
             },
             since_when: fs::kFSEventStreamEventIdSinceNow,
             latency: 0.0,
             flags: fs::kFSEventStreamCreateFlagFileEvents | fs::kFSEventStreamCreateFlagNoDefer,
-            event_tx: Arc::new(Mutex::new(EventTx::Raw { tx: tx })),
+            event_tx: Arc::new(Mutex::new(event_tx)),
             runloop: None,
+            stream: None,
             context: None,
             recursive_info: HashMap::new(),
-        })
+            thread_name: config.thread_name().map(|name| name.to_owned()),
+            registry: registry,
+            released: released,
+        }
     }
+}
 
-    fn new(tx: Sender<DebouncedEvent>, delay: Duration) -> Result<FsEventWatcher> {
-        Ok(FsEventWatcher {
-            paths: unsafe {
-                cf::CFArrayCreateMutable(cf::kCFAllocatorDefault, 0, &cf::kCFTypeArrayCallBacks)
-            },
-            since_when: fs::kFSEventStreamEventIdSinceNow,
-            latency: 0.0,
-            flags: fs::kFSEventStreamCreateFlagFileEvents | fs::kFSEventStreamCreateFlagNoDefer,
-            event_tx: Arc::new(Mutex::new(EventTx::Debounced {
-                tx: tx.clone(),
-                debounce: Debounce::new(delay, tx),
-            })),
-            runloop: None,
-            context: None,
-            recursive_info: HashMap::new(),
-        })
+impl Watcher for FsEventWatcher {
+    fn new_raw_with_config<H: EventHandler<RawEvent>>(handler: H,
+                                                      config: Config)
+                                                      -> Result<FsEventWatcher> {
+        try!(config::check_supported(&config, SUPPORTED_OPTIONS, false));
+        Ok(FsEventWatcher::create(EventTx::new_raw(handler, &config), &config))
     }
 
-    fn watch<P: AsRef<Path>>(&mut self, path: P, recursive_mode: RecursiveMode) -> Result<()> {
+    fn new_with_config<H: EventHandler<DebouncedEvent>>(handler: H,
+                                                        config: Config)
+                                                        -> Result<FsEventWatcher> {
+        try!(config::check_supported(&config, SUPPORTED_OPTIONS, true));
+        let event_tx = try!(EventTx::new_debounced(handler, &config));
+        Ok(FsEventWatcher::create(event_tx, &config))
+    }
+
+    fn watch_filtered<P: AsRef<Path>>(&mut self,
+                                      path: P,
+                                      recursive_mode: RecursiveMode,
+                                      filter: Filter)
+                                      -> Result<WatchId> {
+        // the path is canonicalized when it is added
+        if !path.as_ref().exists() {
+            return Err(Error::PathNotFound(path.as_ref().to_owned()));
+        }
         self.stop();
-        self.append_path(path, recursive_mode);
-        self.run()
+        self.remove_released();
+        let id = self.registry.register(path.as_ref().to_owned());
+        self.append_path(path.as_ref(), watch_id::id(&id), recursive_mode, filter);
+        match self.run() {
+            Ok(()) => Ok(id),
+            Err(e) => {
+                self.registry.discard(id);
+                Err(e)
+            }
+        }
     }
 
     fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         self.stop();
-        let result = self.remove_path(path);
+        self.remove_released();
+        let result = self.remove_path(path.as_ref());
+        if result.is_ok() {
+            self.registry.forget(path.as_ref());
+        }
         // ignore return error: may be empty path list
         let _ = self.run();
         result
     }
+
+    fn watch_info(&self) -> Result<Vec<WatchInfo>> {
+        Ok(self.recursive_info
+            .iter()
+            .map(|(path, &is_recursive)| {
+                WatchInfo {
+                    path: path.clone(),
+                    recursive_mode: RecursiveMode::from_recursive(is_recursive),
+                    watch_count: 1,
+                    // the stream keeps watching a removed path, but nothing will happen there
+                    health: if path.exists() {
+                        WatchHealth::Healthy
+                    } else {
+                        WatchHealth::Lost
+                    },
+                }
+            })
+            .collect())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        if let Ok(mut event_tx) = self.event_tx.lock() {
+            event_tx.pause();
+        }
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        if let Ok(mut event_tx) = self.event_tx.lock() {
+            event_tx.resume();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let Some(stream) = self.stream {
+            // calls the callback for all pending events before returning
+            unsafe {
+                FSEventStreamFlushSync(stream as fs::FSEventStreamRef);
+            }
+        }
+        if let Ok(mut event_tx) = self.event_tx.lock() {
+            event_tx.flush();
+        }
+        Ok(())
+    }
 }
 
 impl Drop for FsEventWatcher {
@@ -381,7 +570,7 @@ fn test_fsevent_watcher_drop() {
 
     {
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).unwrap();
-        watcher.watch("../../", RecursiveMode::Recursive).unwrap();
+        let _watch = watcher.watch("../../", RecursiveMode::Recursive).unwrap();
         thread::sleep(Duration::from_millis(2000));
         println!("is running -> {}", watcher.is_running());
 
@@ -1,6 +1,12 @@
+//! Flags of inotify watches
+//!
+//! Used with
+//! [`INotifyWatcher::watch_with_mask`](../struct.INotifyWatcher.html#method.watch_with_mask).
+
 #![allow(dead_code)]
 
 bitflags! {
+  #[doc = " Mask of an inotify watch, the events to report and the options of the watch"]
   pub flags Mask: u32 {
     #[doc = " Event: File was accessed."]
     #[doc = " "]
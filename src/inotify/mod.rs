@@ -6,52 +6,118 @@
 
 extern crate inotify as inotify_sys;
 extern crate libc;
-extern crate walkdir;
 
-use mio::{self, EventLoop};
+use mio::{self, EventLoop, EventLoopConfig};
 use self::inotify_sys::wrapper::{self, INotify, Watch};
-use self::walkdir::WalkDir;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::fs::metadata;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::sync::mpsc::{self, Sender};
 use std::thread::Builder as ThreadBuilder;
 use std::time::Duration;
-use super::{Error, RawEvent, DebouncedEvent, op, Op, Result, Watcher, RecursiveMode};
-use super::debounce::{Debounce, EventTx};
+use super::{Config, Error, Filter, RawEvent, DebouncedEvent, EventHandler, EventKind, op, Op, Result,
+            Watcher, RecursiveMode, SymlinkPolicy, WatchId, WatchInfo, WatchHealth};
+use super::event::{AccessKind, AccessMode, MetadataKind, ModifyKind, RemoveKind, RenameMode};
+use super::config;
+use super::debounce::EventTx;
+use super::filter;
+use super::symlink;
+use super::watch_id::{self, WatchRegistry};
 
-mod flags;
+pub mod flags;
 
 const INOTIFY: mio::Token = mio::Token(0);
 
+/// Time a move out of a watched directory waits for the matching move into one by default
+const RENAME_TIMEOUT_MS: u64 = 50;
+
+/// Options supported by the `INotifyWatcher`
+const SUPPORTED_OPTIONS: &'static [&'static str] = &["access_events",
+                                                     "pending_watches",
+                                                     "persistent_roots",
+                                                     "relative_paths",
+                                                     "rename_timeout",
+                                                     "symlink_policy",
+                                                     "thread_name"];
+
 /// Watcher implementation based on inotify
-pub struct INotifyWatcher(mio::Sender<EventLoopMsg>);
+pub struct INotifyWatcher(mio::Sender<EventLoopMsg>, WatchRegistry);
 
 struct INotifyHandler {
     inotify: Option<INotify>,
     event_tx: EventTx,
     watches: HashMap<PathBuf, (Watch, flags::Mask, bool)>,
     paths: HashMap<Watch, PathBuf>,
+    /// Watched paths and whether they are watched recursively
+    roots: HashMap<PathBuf, bool>,
+    /// Directories inside recursively watched paths that could not be watched
+    failed: HashSet<PathBuf>,
+    /// Watched paths that don't exist yet, whether they are watched recursively and the existing
+    /// ancestor watched in their place
+    pending: HashMap<PathBuf, (bool, PathBuf)>,
+    pending_watches: bool,
+    persistent_roots: bool,
+    symlink_policy: SymlinkPolicy,
+    /// Whether access events are reported for all watched paths
+    access_events: bool,
+    /// Watched paths added with a custom mask
+    masks: HashMap<PathBuf, flags::Mask>,
+    /// Recursive mode and custom mask of the handles of every watched path, by handle id
+    specs: HashMap<PathBuf, HashMap<u64, (bool, Option<flags::Mask>)>>,
+    /// Move out of a watched directory whose matching move into one hasn't been read yet
+    rename_event: Option<RawEvent>,
+    rename_timeout: u64,
+    rename_timer: Option<mio::Timeout>,
 }
 
 enum EventLoopMsg {
-    AddWatch(PathBuf, RecursiveMode, Sender<Result<()>>),
+    AddWatch(PathBuf, u64, RecursiveMode, Filter, Option<flags::Mask>, Sender<Result<()>>),
     RemoveWatch(PathBuf, Sender<Result<()>>),
+    /// The watched path of the handle may have been renamed since it has been added
+    RemoveHandle(u64, Sender<Result<()>>),
+    WatchInfo(Sender<Vec<WatchInfo>>),
+    Pause(Sender<()>),
+    Resume(Sender<()>),
+    Flush(Sender<()>),
     Shutdown,
 }
 
+/// Report a move without a matching half as `REMOVE`, removing the watches of the moved path
 #[inline]
-fn send_pending_rename_event(event: Option<RawEvent>, event_tx: &mut EventTx) {
+fn send_pending_rename_event(event: Option<RawEvent>,
+                             batch: &mut Vec<RawEvent>,
+                             watches: &HashMap<PathBuf, (Watch, flags::Mask, bool)>,
+                             remove_watches: &mut Vec<PathBuf>) {
     if let Some(e) = event {
-        event_tx.send(RawEvent {
+        remove_watch_by_event(&e.path, watches, remove_watches);
+        batch.push(RawEvent {
             path: e.path,
             op: Ok(op::REMOVE),
+            kind: EventKind::Remove(RemoveKind::Any),
             cookie: None,
+            relative: None,
         });
     }
 }
 
+/// Detailed kind of an inotify event that has been translated to `o`
+fn event_kind(event: &wrapper::Event, o: Op) -> EventKind {
+    match o {
+        op::CREATE => EventKind::create(event.is_dir()),
+        // IN_ISDIR isn't set for the watched path itself
+        op::REMOVE if event.is_delete_self() => EventKind::Remove(RemoveKind::Any),
+        op::REMOVE => EventKind::remove(event.is_dir()),
+        op::RENAME if event.is_moved_to() => EventKind::Rename(RenameMode::To),
+        op::WRITE => EventKind::Modify(ModifyKind::Data),
+        op::CHMOD => EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)),
+        op::CLOSE_WRITE => EventKind::Access(AccessKind::Close(AccessMode::Write)),
+        op::CLOSE_NOWRITE => EventKind::Access(AccessKind::Close(AccessMode::Read)),
+        _ => EventKind::from_op(o),
+    }
+}
+
 #[inline]
 fn add_watch_by_event(path: &Option<PathBuf>,
                       event: &wrapper::Event,
@@ -70,6 +136,120 @@ fn add_watch_by_event(path: &Option<PathBuf>,
     }
 }
 
+/// Custom mask of the innermost watched path containing `path`, if it has been added with one
+fn custom_mask(masks: &HashMap<PathBuf, flags::Mask>,
+               roots: &HashMap<PathBuf, bool>,
+               path: &Path)
+               -> Option<flags::Mask> {
+    let mut dir = Some(path);
+    while let Some(d) = dir {
+        if let Some(&mask) = masks.get(d) {
+            return Some(mask);
+        }
+        if roots.contains_key(d) {
+            return None;
+        }
+        dir = d.parent();
+    }
+    None
+}
+
+/// Combine the recursive modes and custom masks of the handles of a watched path
+///
+/// The path is watched recursively if any of its handles is recursive. It is watched with a custom
+/// mask only if all handles have one, requesting the events of all masks and the options, like
+/// `IN_ONESHOT`, they have in common.
+fn combine_specs(specs: &HashMap<u64, (bool, Option<flags::Mask>)>)
+                 -> (bool, Option<flags::Mask>) {
+    let options = flags::IN_ONLYDIR | flags::IN_DONT_FOLLOW | flags::IN_EXCL_UNLINK |
+                  flags::IN_ONESHOT;
+    let is_recursive = specs.values().any(|&(is_recursive, _)| is_recursive);
+    let mut combined: Option<flags::Mask> = None;
+    for &(_, mask) in specs.values() {
+        let mask = match mask {
+            Some(mask) => mask,
+            None => return (is_recursive, None),
+        };
+        combined = Some(match combined {
+            Some(c) => ((c | mask) & !options) | (c & mask & options),
+            None => mask,
+        });
+    }
+    (is_recursive, combined)
+}
+
+/// Whether a directory is part of a watched path, rather than only watched for pending paths
+fn is_watched_dir(roots: &HashMap<PathBuf, bool>, dir: &Path) -> bool {
+    roots.iter().any(|(root, &is_recursive)| dir == root || is_recursive && dir.starts_with(root))
+}
+
+/// Rescan a recursively watched directory after one of its ignore files changed, the new rules may
+/// no longer ignore some of its subdirectories
+#[inline]
+fn add_watch_by_ignore_file(path: &Option<PathBuf>,
+                            watches: &HashMap<PathBuf, (Watch, flags::Mask, bool)>,
+                            rescan_watches: &mut Vec<PathBuf>) {
+    if let Some(ref path) = *path {
+        if filter::is_ignore_file(path) {
+            if let Some(dir) = path.parent() {
+                if let Some(&(_, _, true)) = watches.get(dir) {
+                    if !rescan_watches.iter().any(|p| p == dir) {
+                        rescan_watches.push(dir.to_owned());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The path of `path` after `from` has been renamed to `to`
+fn renamed_path(path: &Path, from: &Path, to: &Path) -> PathBuf {
+    match path.strip_prefix(from) {
+        Ok(relative) if !relative.as_os_str().is_empty() => to.join(relative),
+        _ => to.to_owned(),
+    }
+}
+
+/// Move the entries of a renamed path and the paths inside it to their new paths
+fn rename_keys<V>(map: &mut HashMap<PathBuf, V>, from: &Path, to: &Path) {
+    let moved: Vec<PathBuf> = map.keys().filter(|path| path.starts_with(from)).cloned().collect();
+    for old in moved {
+        if let Some(value) = map.remove(&old) {
+            map.insert(renamed_path(&old, from, to), value);
+        }
+    }
+}
+
+/// Move the watches of a directory renamed inside the recursive watches to its new path, returns
+/// false if it hasn't been watched or is no longer inside a recursive watch
+///
+/// The watches are moved regardless of the new parent if `contains_root` is set, watched paths
+/// keep their watches wherever they are moved.
+fn rename_watches(from: &Path,
+                  to: &Path,
+                  contains_root: bool,
+                  watches: &mut HashMap<PathBuf, (Watch, flags::Mask, bool)>,
+                  paths: &mut HashMap<Watch, PathBuf>)
+                  -> bool {
+    if !contains_root {
+        if !watches.contains_key(from) {
+            return false;
+        }
+        match to.parent().and_then(|parent| watches.get(parent)) {
+            Some(&(_, _, true)) => (),
+            _ => return false,
+        }
+    }
+
+    rename_keys(watches, from, to);
+    for (path, &(w, _, _)) in watches.iter() {
+        if path.starts_with(to) {
+            paths.insert(w, path.clone());
+        }
+    }
+    true
+}
+
 #[inline]
 fn remove_watch_by_event(path: &Option<PathBuf>,
                          watches: &HashMap<PathBuf, (Watch, flags::Mask, bool)>,
@@ -81,213 +261,677 @@ fn remove_watch_by_event(path: &Option<PathBuf>,
     }
 }
 
+/// Convert a duration to milliseconds, for the timeouts of the event loop
+fn duration_ms(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + duration.subsec_nanos() as u64 / 1_000_000
+}
+
 impl mio::Handler for INotifyHandler {
     type Timeout = ();
     type Message = EventLoopMsg;
 
     fn ready(&mut self,
-             _event_loop: &mut EventLoop<INotifyHandler>,
+             event_loop: &mut EventLoop<INotifyHandler>,
              token: mio::Token,
              events: mio::EventSet) {
         match token {
             INOTIFY => {
                 assert!(events.is_readable());
 
-                let mut add_watches = Vec::new();
-                let mut remove_watches = Vec::new();
+                // the events may have been read by a flush already
+                self.handle_events(event_loop);
+            }
+            _ => unreachable!(),
+        }
+    }
 
-                if let Some(ref mut inotify) = self.inotify {
-                    match inotify.available_events() {
-                        Ok(events) => {
-                            assert!(!events.is_empty());
+    fn timeout(&mut self, event_loop: &mut EventLoop<INotifyHandler>, _: ()) {
+        self.rename_timer = None;
+        // the matching half may have been queued but not read yet
+        while self.handle_events(event_loop) {}
+        self.expire_rename_event(event_loop);
+    }
+
+    fn notify(&mut self, event_loop: &mut EventLoop<INotifyHandler>, msg: EventLoopMsg) {
+        match msg {
+            EventLoopMsg::AddWatch(path, id, recursive_mode, filter, mask, tx) => {
+                let is_recursive = recursive_mode.is_recursive();
+                self.event_tx.filters_mut().insert(path.clone(), id, filter, is_recursive);
+                self.specs
+                    .entry(path.clone())
+                    .or_insert_with(HashMap::new)
+                    .insert(id, (is_recursive, mask));
+                let result = if self.roots.contains_key(&path) {
+                    // another handle of a watched path
+                    self.update_root(path.clone())
+                } else {
+                    if let Some(mask) = mask {
+                        self.masks.insert(path.clone(), mask);
+                    }
+                    let result = if self.pending_watches && !path.exists() {
+                        self.add_pending(path.clone(), is_recursive)
+                    } else {
+                        self.add_watch(path.clone(), is_recursive, true)
+                    };
+                    if result.is_ok() {
+                        self.roots.insert(path.clone(), is_recursive);
+                    }
+                    result
+                };
+                if result.is_err() {
+                    if self.roots.contains_key(&path) {
+                        // restores the watches of the other handles
+                        let _ = self.remove_handle(id);
+                    } else {
+                        self.forget_root(&path);
+                    }
+                }
+                let _ = tx.send(result);
+            }
+            EventLoopMsg::RemoveWatch(path, tx) => {
+                let result = self.remove_root(path);
+                let _ = tx.send(result);
+            }
+            EventLoopMsg::RemoveHandle(id, tx) => {
+                let result = self.remove_handle(id);
+                let _ = tx.send(result);
+            }
+            EventLoopMsg::WatchInfo(tx) => {
+                let _ = tx.send(self.watch_info());
+            }
+            EventLoopMsg::Pause(tx) => {
+                self.event_tx.pause();
+                let _ = tx.send(());
+            }
+            EventLoopMsg::Resume(tx) => {
+                self.event_tx.resume();
+                let _ = tx.send(());
+            }
+            EventLoopMsg::Flush(tx) => {
+                // inotify queues the events as the changes are made, so reading them is enough
+                while self.handle_events(event_loop) {}
+                // both halves of a rename are queued at once, after reading all events a move
+                // without a match won't get one
+                self.expire_rename_event(event_loop);
+                self.event_tx.flush();
+                let _ = tx.send(());
+            }
+            EventLoopMsg::Shutdown => {
+                let _ = self.remove_all_watches();
+                if let Some(inotify) = self.inotify.take() {
+                    let _ = inotify.close();
+                }
+                event_loop.shutdown();
+            }
+        }
+    }
+}
 
-                            let mut rename_event = None;
+/// Whether a walked entry is a directory
+fn is_dir_entry(entry: &symlink::Entry) -> bool {
+    entry.metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false)
+}
 
-                            for event in events {
-                                if event.is_queue_overflow() {
-                                    self.event_tx.send(RawEvent {
-                                        path: None,
-                                        op: Ok(op::RESCAN),
+/// Remove a watch, unless the kernel has removed it already
+///
+/// The kernel removes a watch before its `IN_IGNORED` event has been read.
+fn rm_watch(inotify: &INotify, w: Watch) -> Result<()> {
+    match inotify.rm_watch(w) {
+        Err(ref e) if e.raw_os_error() == Some(libc::EINVAL) => Ok(()),
+        result => result.map_err(Error::Io),
+    }
+}
+
+/// Translate an error of `inotify_add_watch` for `path`
+fn add_watch_error(err: io::Error, path: &Path) -> Error {
+    if err.raw_os_error() == Some(libc::ENOSPC) {
+        // inotify reports an exhausted `max_user_watches` as ENOSPC
+        Error::MaxFilesWatch(path.to_owned())
+    } else {
+        Error::for_path(err, path)
+    }
+}
+
+impl INotifyHandler {
+    /// Read and handle the available events, returns false if there were none
+    fn handle_events(&mut self, event_loop: &mut EventLoop<INotifyHandler>) -> bool {
+        let mut add_watches = Vec::new();
+        // watched directories to walk again after one of their ignore files changed
+        let mut rescan_watches = Vec::new();
+        let mut remove_watches = Vec::new();
+        // created paths and whether their event has been delivered, for the pending watches
+        let mut created = Vec::new();
+        // watches that have been removed by the kernel
+        let mut ignored = Vec::new();
+
+        if let Some(ref mut inotify) = self.inotify {
+            match inotify.available_events() {
+                Ok(events) => {
+                    if events.is_empty() {
+                        return false;
+                    }
+
+                    // the events of one read are delivered as one batch
+                    let mut batch = Vec::new();
+                    let mut rename_event = self.rename_event.take();
+
+                    for event in events {
+                        if event.is_queue_overflow() {
+                            batch.push(RawEvent {
+                                path: None,
+                                op: Ok(op::RESCAN),
+                                kind: EventKind::Rescan,
+                                cookie: None,
+                                relative: None,
+                            });
+                        }
+
+                        let path = if event.name.is_empty() {
+                            match self.paths.get(&event.wd) {
+                                Some(p) => Some(p.clone()),
+                                None => None,
+                            }
+                        } else {
+                            self.paths.get(&event.wd).map(|root| root.join(&event.name))
+                        };
+
+                        // the kernel removes a watch once its path has been deleted or unmounted
+                        // or a oneshot watch has fired, watches removed by the watcher itself are
+                        // already unknown
+                        if event.is_unmount() || event.is_ignored() {
+                            if event.is_ignored() {
+                                ignored.push(event.wd);
+                            }
+                            if let Some(path) = path {
+                                if self.roots.contains_key(&path) {
+                                    let o = if event.is_unmount() {
+                                        op::UNMOUNTED
+                                    } else {
+                                        op::WATCH_REMOVED
+                                    };
+                                    batch.push(RawEvent {
+                                        path: Some(path),
+                                        op: Ok(o),
+                                        kind: EventKind::from_op(o),
                                         cookie: None,
+                                        relative: None,
                                     });
                                 }
+                            }
+                            continue;
+                        }
 
-                                let path = if event.name.is_empty() {
-                                    match self.paths.get(&event.wd) {
-                                        Some(p) => Some(p.clone()),
-                                        None => None,
-                                    }
-                                } else {
-                                    self.paths.get(&event.wd).map(|root| root.join(&event.name))
-                                };
+                        if !self.pending.is_empty() {
+                            // events of directories only watched for pending paths aren't
+                            // delivered, except for the pending paths themselves
+                            let roots = &self.roots;
+                            let pending = &self.pending;
+                            let is_pending = path.as_ref()
+                                .map_or(false, |path| pending.contains_key(path));
+                            let delivered = is_pending ||
+                                            self.paths
+                                .get(&event.wd)
+                                .map_or(true, |dir| is_watched_dir(roots, dir));
+                            if event.is_create() || event.is_moved_to() {
+                                if let Some(ref path) = path {
+                                    created.push((path.clone(), delivered));
+                                }
+                            }
+                            if !delivered {
+                                continue;
+                            }
+                        }
 
-                                if event.is_moved_from() {
-                                    send_pending_rename_event(rename_event, &mut self.event_tx);
-                                    remove_watch_by_event(&path,
-                                                          &self.watches,
-                                                          &mut remove_watches);
-                                    rename_event = Some(RawEvent {
-                                        path: path,
-                                        op: Ok(op::RENAME),
-                                        cookie: Some(event.cookie),
-                                    });
-                                } else {
-                                    let mut o = Op::empty();
-                                    let mut c = None;
-                                    if event.is_moved_to() {
-                                        if let Some(e) = rename_event {
-                                            if e.cookie == Some(event.cookie) {
-                                                self.event_tx.send(e);
-                                                o.insert(op::RENAME);
-                                                c = Some(event.cookie);
-                                            } else {
-                                                o.insert(op::CREATE);
+                        add_watch_by_ignore_file(&path, &self.watches, &mut rescan_watches);
+
+                        if event.is_moved_from() {
+                            send_pending_rename_event(rename_event,
+                                                      &mut batch,
+                                                      &self.watches,
+                                                      &mut remove_watches);
+                            rename_event = Some(RawEvent {
+                                path: path,
+                                op: Ok(op::RENAME),
+                                kind: EventKind::Rename(RenameMode::From),
+                                cookie: Some(event.cookie),
+                                relative: None,
+                            });
+                        } else {
+                            let mut o = Op::empty();
+                            let mut c = None;
+                            if event.is_moved_to() {
+                                let mut renamed = false;
+                                if let Some(e) = rename_event {
+                                    if e.cookie == Some(event.cookie) {
+                                        if let (Some(from), Some(to)) = (e.path.as_ref(),
+                                                                         path.as_ref()) {
+                                            // watched paths inside the renamed directory move
+                                            // along with it
+                                            let contains_root = self.roots
+                                                .keys()
+                                                .any(|root| root.starts_with(from));
+                                            if contains_root {
+                                                rename_keys(&mut self.roots, from, to);
+                                                rename_keys(&mut self.specs, from, to);
+                                                rename_keys(&mut self.masks, from, to);
+                                                self.event_tx.filters_mut().rename(from, to);
                                             }
-                                        } else {
-                                            o.insert(op::CREATE);
+                                            renamed = rename_watches(from,
+                                                                     to,
+                                                                     contains_root,
+                                                                     &mut self.watches,
+                                                                     &mut self.paths);
                                         }
-                                        rename_event = None;
-                                        add_watch_by_event(&path,
-                                                           event,
-                                                           &self.watches,
-                                                           &mut add_watches);
-                                    }
-                                    if event.is_move_self() {
+                                        if !renamed {
+                                            remove_watch_by_event(&e.path,
+                                                                  &self.watches,
+                                                                  &mut remove_watches);
+                                        }
+                                        batch.push(e);
                                         o.insert(op::RENAME);
-                                    }
-                                    if event.is_create() {
+                                        c = Some(event.cookie);
+                                    } else {
+                                        send_pending_rename_event(Some(e),
+                                                                  &mut batch,
+                                                                  &self.watches,
+                                                                  &mut remove_watches);
                                         o.insert(op::CREATE);
-                                        add_watch_by_event(&path,
-                                                           event,
-                                                           &self.watches,
-                                                           &mut add_watches);
-                                    }
-                                    if event.is_delete_self() || event.is_delete() {
-                                        o.insert(op::REMOVE);
-                                        remove_watch_by_event(&path,
-                                                              &self.watches,
-                                                              &mut remove_watches);
-                                    }
-                                    if event.is_modify() {
-                                        o.insert(op::WRITE);
                                     }
-                                    if event.is_close_write() {
-                                        o.insert(op::CLOSE_WRITE);
+                                } else {
+                                    o.insert(op::CREATE);
+                                }
+                                rename_event = None;
+                                if !renamed {
+                                    add_watch_by_event(&path,
+                                                       event,
+                                                       &self.watches,
+                                                       &mut add_watches);
+                                }
+                            }
+                            if event.is_move_self() {
+                                // the watch keeps the old path, unless the move has been
+                                // reported by a watched parent as well
+                                o.insert(op::RENAME);
+                            }
+                            if event.is_create() {
+                                o.insert(op::CREATE);
+                                add_watch_by_event(&path,
+                                                   event,
+                                                   &self.watches,
+                                                   &mut add_watches);
+                            }
+                            if event.is_delete_self() || event.is_delete() {
+                                o.insert(op::REMOVE);
+                                remove_watch_by_event(&path,
+                                                      &self.watches,
+                                                      &mut remove_watches);
+                            }
+                            if event.is_modify() {
+                                o.insert(op::WRITE);
+                            }
+                            if event.is_close_write() {
+                                o.insert(op::CLOSE_WRITE);
+                            }
+                            if event.is_attrib() {
+                                o.insert(op::CHMOD);
+                            }
+                            // a directory may be watched for access events because of another
+                            // watched path
+                            if event.is_access() || event.is_open() || event.is_close_nowrite() {
+                                let masks = &self.masks;
+                                let roots = &self.roots;
+                                let reported = self.access_events ||
+                                               path.as_ref().map_or(false, |path| {
+                                    custom_mask(masks, roots, path).is_some()
+                                });
+                                if reported {
+                                    if event.is_access() {
+                                        o.insert(op::ACCESS);
                                     }
-                                    if event.is_attrib() {
-                                        o.insert(op::CHMOD);
+                                    if event.is_open() {
+                                        o.insert(op::OPEN);
                                     }
-
-                                    if !o.is_empty() {
-                                        send_pending_rename_event(rename_event, &mut self.event_tx);
-                                        rename_event = None;
-
-                                        self.event_tx.send(RawEvent {
-                                            path: path,
-                                            op: Ok(o),
-                                            cookie: c,
-                                        });
+                                    if event.is_close_nowrite() {
+                                        o.insert(op::CLOSE_NOWRITE);
                                     }
                                 }
                             }
 
-                            send_pending_rename_event(rename_event, &mut self.event_tx);
-                        }
-                        Err(e) => {
-                            self.event_tx.send(RawEvent {
-                                path: None,
-                                op: Err(Error::Io(e)),
-                                cookie: None,
-                            });
+                            if !o.is_empty() {
+                                send_pending_rename_event(rename_event,
+                                                          &mut batch,
+                                                          &self.watches,
+                                                          &mut remove_watches);
+                                rename_event = None;
+
+                                batch.push(RawEvent {
+                                    path: path,
+                                    op: Ok(o),
+                                    kind: event_kind(event, o),
+                                    cookie: c,
+                                    relative: None,
+                                });
+                            }
                         }
                     }
+
+                    // the matching half may only be part of the next read
+                    self.rename_event = rename_event;
+                    self.event_tx.send_all(batch);
+                }
+                Err(e) => {
+                    self.event_tx.send(RawEvent {
+                        path: None,
+                        op: Err(Error::Io(e)),
+                        kind: EventKind::Any,
+                        cookie: None,
+                        relative: None,
+                    });
+                    return false;
                 }
+            }
+        } else {
+            return false;
+        }
 
-                for path in remove_watches {
-                    let _ = self.remove_watch(path, true);
+        if self.rename_event.is_some() {
+            if self.rename_timer.is_none() {
+                match event_loop.timeout_ms((), self.rename_timeout) {
+                    Ok(timer) => self.rename_timer = Some(timer),
+                    Err(_) => self.expire_rename_event(event_loop),
                 }
+            }
+        } else if let Some(timer) = self.rename_timer.take() {
+            event_loop.clear_timeout(timer);
+        }
 
-                for path in add_watches {
-                    let _ = self.add_watch(path, true, false);
+        for wd in ignored {
+            if let Some(path) = self.paths.remove(&wd) {
+                if self.watches.get(&path).map_or(false, |&(w, _, _)| w == wd) {
+                    self.watches.remove(&path);
                 }
             }
-            _ => unreachable!(),
         }
-    }
 
-    fn notify(&mut self, event_loop: &mut EventLoop<INotifyHandler>, msg: EventLoopMsg) {
-        match msg {
-            EventLoopMsg::AddWatch(path, recursive_mode, tx) => {
-                let _ = tx.send(self.add_watch(path, recursive_mode.is_recursive(), true));
+        self.remove_event_watches(remove_watches);
+
+        for path in rescan_watches {
+            if self.add_watch(path.clone(), true, false).is_err() {
+                self.failed.insert(path);
             }
-            EventLoopMsg::RemoveWatch(path, tx) => {
-                let _ = tx.send(self.remove_watch(path, false));
+        }
+
+        // the contents of new directories may have been created before they were watched
+        let mut batch = Vec::new();
+        for path in add_watches {
+            match self.add_created_dir(&path) {
+                Ok(entries) => {
+                    batch.extend(entries.into_iter().map(|entry| {
+                        let is_dir = entry.metadata.map(|m| m.is_dir()).unwrap_or(false);
+                        RawEvent {
+                            path: Some(entry.path),
+                            op: Ok(op::CREATE),
+                            kind: EventKind::create(is_dir),
+                            cookie: None,
+                            relative: None,
+                        }
+                    }));
+                }
+                Err(_) => {
+                    self.failed.insert(path);
+                }
             }
-            EventLoopMsg::Shutdown => {
-                let _ = self.remove_all_watches();
-                if let Some(inotify) = self.inotify.take() {
-                    let _ = inotify.close();
+        }
+        self.event_tx.send_all(batch);
+
+        for (path, delivered) in created {
+            self.upgrade_pending(&path, delivered);
+        }
+
+        true
+    }
+
+    /// Report a move whose matching half hasn't been read as `REMOVE`
+    fn expire_rename_event(&mut self, event_loop: &mut EventLoop<INotifyHandler>) {
+        if let Some(timer) = self.rename_timer.take() {
+            event_loop.clear_timeout(timer);
+        }
+        if self.rename_event.is_none() {
+            return;
+        }
+
+        let mut batch = Vec::new();
+        let mut remove_watches = Vec::new();
+        send_pending_rename_event(self.rename_event.take(),
+                                  &mut batch,
+                                  &self.watches,
+                                  &mut remove_watches);
+        self.event_tx.send_all(batch);
+        self.remove_event_watches(remove_watches);
+    }
+
+    /// Remove the watches of paths that have been removed or moved away
+    fn remove_event_watches(&mut self, paths: Vec<PathBuf>) {
+        for path in paths {
+            let _ = self.remove_watch(path.clone(), true);
+            if self.persistent_roots {
+                if let Some(&is_recursive) = self.roots.get(&path) {
+                    self.restore_root(path, is_recursive);
                 }
-                event_loop.shutdown();
             }
         }
     }
-}
 
-/// return `DirEntry` when it is a directory
-fn filter_dir(e: walkdir::Result<walkdir::DirEntry>) -> Option<walkdir::DirEntry> {
-    if let Ok(e) = e {
-        if let Ok(metadata) = e.metadata() {
-            if metadata.is_dir() {
-                return Some(e);
+    /// Watch the nearest existing ancestor of a watched path that doesn't exist yet
+    fn add_pending(&mut self, path: PathBuf, is_recursive: bool) -> Result<()> {
+        let mut dir = path.parent();
+        while let Some(d) = dir {
+            if d.is_dir() {
+                break;
+            }
+            dir = d.parent();
+        }
+        let dir = match dir {
+            Some(dir) => dir.to_owned(),
+            None => return Err(Error::PathNotFound(path)),
+        };
+        try!(self.add_single_watch(dir.clone(), false, false));
+        self.pending.insert(path, (is_recursive, dir));
+        Ok(())
+    }
+
+    /// Update the pending watches after `path` has been created, watching the pending paths that
+    /// exist now
+    fn upgrade_pending(&mut self, path: &Path, delivered: bool) {
+        let affected: Vec<PathBuf> = self.pending
+            .keys()
+            .filter(|pending| pending.starts_with(path))
+            .cloned()
+            .collect();
+
+        for pending in affected {
+            let (is_recursive, dir) = match self.pending.remove(&pending) {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            if !pending.exists() {
+                // a parent directory has been created
+                let _ = self.add_pending(pending, is_recursive);
+            } else if self.add_watch(pending.clone(), is_recursive, true).is_ok() {
+                // the event of the path itself may not have been delivered
+                if !(delivered && pending == path) {
+                    self.event_tx.send(RawEvent {
+                        kind: EventKind::create(pending.is_dir()),
+                        path: Some(pending),
+                        op: Ok(op::CREATE),
+                        cookie: None,
+                        relative: None,
+                    });
+                }
+            } else {
+                let _ = self.add_pending(pending, is_recursive);
             }
+
+            self.remove_helper(&dir);
+        }
+    }
+
+    /// Watch a removed watched path again once it has been recreated
+    fn restore_root(&mut self, path: PathBuf, is_recursive: bool) {
+        if self.pending.contains_key(&path) ||
+           self.add_pending(path.clone(), is_recursive).is_err() {
+            return;
+        }
+        // it may have been recreated already, e.g. by renaming another file to it
+        if path.exists() {
+            self.upgrade_pending(&path, false);
+        }
+    }
+
+    /// Remove the watch of a directory once it is no longer needed for any pending path
+    fn remove_helper(&mut self, dir: &Path) {
+        let needed = is_watched_dir(&self.roots, dir) ||
+                     self.pending.values().any(|&(_, ref d)| d == dir);
+        if !needed {
+            let _ = self.remove_watch(dir.to_owned(), false);
         }
     }
-    None
-}
 
-impl INotifyHandler {
     fn add_watch(&mut self, path: PathBuf, is_recursive: bool, mut watch_self: bool) -> Result<()> {
-        let metadata = try!(metadata(&path).map_err(Error::Io));
+        let metadata = try!(symlink::metadata(&path, self.symlink_policy)
+            .map_err(|e| Error::for_path(e, &path)));
 
         if !metadata.is_dir() || !is_recursive {
             return self.add_single_watch(path, false, true);
         }
 
-        for entry in WalkDir::new(path)
-            .follow_links(true)
+        let dirs: Vec<PathBuf> = self.walk(&path)
             .into_iter()
-            .filter_map(filter_dir) {
-            try!(self.add_single_watch(entry.path().to_path_buf(), is_recursive, watch_self));
+            .filter(is_dir_entry)
+            .map(|e| e.path)
+            .collect();
+
+        let mut added = Vec::new();
+        for dir in dirs {
+            let old = self.watches.get(&dir).cloned();
+            if let Err(e) = self.add_single_watch(dir.clone(), is_recursive, watch_self) {
+                // the watches added so far would use up descriptors for the lifetime of the watcher
+                self.undo_watches(added);
+                return Err(e);
+            }
+            added.push((dir, old));
             watch_self = false;
         }
 
         Ok(())
     }
 
+    /// Restore the watches of directories to their state before they have been added to
+    fn undo_watches(&mut self, added: Vec<(PathBuf, Option<(Watch, flags::Mask, bool)>)>) {
+        if let Some(ref inotify) = self.inotify {
+            for (dir, old) in added.into_iter().rev() {
+                match old {
+                    Some((w, flags, is_recursive)) => {
+                        // the mask may have been extended, it is replaced by the old one
+                        let _ = inotify.add_watch(&dir, flags.bits());
+                        self.watches.insert(dir, (w, flags, is_recursive));
+                    }
+                    None => {
+                        if let Some((w, _, _)) = self.watches.remove(&dir) {
+                            let _ = rm_watch(inotify, w);
+                            self.paths.remove(&w);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Watch a directory created inside a recursive watch, returns the entries found below it
+    ///
+    /// The directory is walked again once its watches are in place, until no new directories turn
+    /// up, so entries created before they were watched are found as well. Entries created while
+    /// walking may be reported twice.
+    fn add_created_dir(&mut self, path: &Path) -> Result<Vec<symlink::Entry>> {
+        let mut watched = HashSet::new();
+        loop {
+            let mut entries = self.walk(path);
+            let dirs: Vec<PathBuf> = entries.iter()
+                .filter(|e| is_dir_entry(e) && !watched.contains(&e.path))
+                .map(|e| e.path.clone())
+                .collect();
+
+            if dirs.is_empty() {
+                // the first entry is the directory itself, its event has been delivered already
+                if !entries.is_empty() {
+                    entries.remove(0);
+                }
+                return Ok(entries);
+            }
+
+            for dir in dirs {
+                try!(self.add_single_watch(dir.clone(), true, false));
+                watched.insert(dir);
+            }
+        }
+    }
+
+    /// Walk a path to be watched recursively
+    ///
+    /// Excluded directories are skipped entirely, so they don't use up any watch descriptors.
+    fn walk(&self, path: &Path) -> Vec<symlink::Entry> {
+        let filters = self.event_tx.filters();
+        // directories created inside a watched path are walked relative to its root
+        let root = filters.relative(path).map_or(path, |(root, _)| root);
+        symlink::walk(path, root, usize::max_value(), self.symlink_policy, filters)
+    }
+
     fn add_single_watch(&mut self,
                         path: PathBuf,
                         is_recursive: bool,
                         watch_self: bool)
                         -> Result<()> {
-        let mut flags = flags::IN_ATTRIB | flags::IN_CREATE | flags::IN_DELETE |
-                        flags::IN_CLOSE_WRITE | flags::IN_MODIFY |
-                        flags::IN_MOVED_FROM | flags::IN_MOVED_TO;
+        let mut flags = if let Some(mask) = custom_mask(&self.masks, &self.roots, &path) {
+            // adding to the mask of a directory watched several times is up to the watcher
+            mask & !flags::IN_MASK_ADD
+        } else {
+            let mut flags = flags::IN_ATTRIB | flags::IN_CREATE | flags::IN_DELETE |
+                            flags::IN_CLOSE_WRITE | flags::IN_MODIFY |
+                            flags::IN_MOVED_FROM | flags::IN_MOVED_TO;
+
+            if watch_self {
+                flags.insert(flags::IN_DELETE_SELF);
+                flags.insert(flags::IN_MOVE_SELF);
+            }
 
-        if watch_self {
-            flags.insert(flags::IN_DELETE_SELF);
-            flags.insert(flags::IN_MOVE_SELF);
+            if self.access_events {
+                flags.insert(flags::IN_ACCESS | flags::IN_OPEN | flags::IN_CLOSE_NOWRITE);
+            }
+            flags
+        };
+
+        // links are never followed, a watched path that is a link is watched itself
+        if self.symlink_policy == SymlinkPolicy::WatchLink {
+            flags.insert(flags::IN_DONT_FOLLOW);
         }
 
-        if let Some(&(_, old_flags, _)) = self.watches.get(&path) {
+        let mut is_recursive = is_recursive;
+        if let Some(&(_, old_flags, old_is_recursive)) = self.watches.get(&path) {
             flags.insert(old_flags);
             flags.insert(flags::IN_MASK_ADD);
+            // the directory may be part of another recursive watch
+            is_recursive = is_recursive || old_is_recursive;
         }
 
         if let Some(ref inotify) = self.inotify {
             match inotify.add_watch(&path, flags.bits()) {
-                Err(e) => Err(Error::Io(e)),
+                Err(e) => Err(add_watch_error(e, &path)),
                 Ok(w) => {
+                    if self.paths.get(&w).map_or(false, |p| *p != path) {
+                        // the same directory is already watched through another path
+                        return Ok(());
+                    }
                     flags.remove(flags::IN_MASK_ADD);
                     self.watches.insert(path.clone(), (w, flags, is_recursive));
                     self.paths.insert(w, path);
@@ -304,14 +948,14 @@ impl INotifyHandler {
             None => return Err(Error::WatchNotFound),
             Some((w, _, is_recursive)) => {
                 if let Some(ref inotify) = self.inotify {
-                    try!(inotify.rm_watch(w).map_err(Error::Io));
+                    try!(rm_watch(inotify, w));
                     self.paths.remove(&w);
 
                     if is_recursive || remove_recursive {
                         let mut remove_list = Vec::new();
                         for (w, p) in &self.paths {
                             if p.starts_with(&path) {
-                                try!(inotify.rm_watch(*w).map_err(Error::Io));
+                                try!(rm_watch(inotify, *w));
                                 self.watches.remove(p);
                                 remove_list.push(*w);
                             }
@@ -326,10 +970,151 @@ impl INotifyHandler {
         Ok(())
     }
 
+    /// Remove the watch of a watched path, keeping the watches of other watched paths inside or
+    /// around it
+    fn remove_root(&mut self, path: PathBuf) -> Result<()> {
+        if let Some((_, dir)) = self.pending.remove(&path) {
+            self.forget_root(&path);
+            self.remove_helper(&dir);
+            return Ok(());
+        }
+
+        match self.remove_watch(path.clone(), false) {
+            Ok(()) => (),
+            // the kernel has removed the watch already, the watches below it may be left
+            Err(Error::WatchNotFound) if self.roots.contains_key(&path) => {
+                let below: Vec<PathBuf> =
+                    self.watches.keys().filter(|p| p.starts_with(&path)).cloned().collect();
+                for p in below {
+                    let _ = self.remove_watch(p, false);
+                }
+            }
+            Err(e) => return Err(e),
+        }
+        self.forget_root(&path);
+        self.restore_overlapping(&path);
+        Ok(())
+    }
+
+    /// Forget a watched path and the filters, masks and specs of its handles
+    fn forget_root(&mut self, path: &Path) {
+        self.roots.remove(path);
+        self.event_tx.filters_mut().remove(path);
+        self.masks.remove(path);
+        self.specs.remove(path);
+    }
+
+    /// Remove a handle of a watched path, the path is removed along with its last handle
+    fn remove_handle(&mut self, id: u64) -> Result<()> {
+        let path = match self.specs.iter().find(|&(_, specs)| specs.contains_key(&id)) {
+            Some((path, _)) => path.clone(),
+            // the path has been unwatched already
+            None => return Ok(()),
+        };
+        let remaining = match self.specs.get_mut(&path) {
+            Some(specs) => {
+                specs.remove(&id);
+                !specs.is_empty()
+            }
+            None => false,
+        };
+        if !remaining {
+            return self.remove_root(path);
+        }
+
+        self.event_tx.filters_mut().remove_handle(&path, id);
+        self.update_root(path)
+    }
+
+    /// Apply the combined recursive mode and mask of the handles of a watched path, adding its
+    /// watches again if they have changed
+    fn update_root(&mut self, path: PathBuf) -> Result<()> {
+        let (is_recursive, mask) = match self.specs.get(&path) {
+            Some(specs) => combine_specs(specs),
+            None => return Ok(()),
+        };
+        if self.roots.get(&path) == Some(&is_recursive) && self.masks.get(&path) == mask.as_ref() {
+            return Ok(());
+        }
+
+        self.roots.insert(path.clone(), is_recursive);
+        match mask {
+            Some(mask) => self.masks.insert(path.clone(), mask),
+            None => self.masks.remove(&path),
+        };
+        if let Some(pending) = self.pending.get_mut(&path) {
+            pending.0 = is_recursive;
+            return Ok(());
+        }
+
+        // the flags of a watch can only be reduced by adding it again
+        let _ = self.remove_watch(path.clone(), false);
+        let result = self.add_watch(path.clone(), is_recursive, true);
+        self.restore_overlapping(&path);
+        result
+    }
+
+    /// Add the watches of the watched paths inside or around `path` again, after its watches
+    /// have been removed
+    fn restore_overlapping(&mut self, path: &Path) {
+        let overlapping: Vec<(PathBuf, bool)> = self.roots
+            .iter()
+            .filter(|&(root, _)| root != path && (root.starts_with(path) || path.starts_with(root)))
+            .map(|(root, &is_recursive)| (root.clone(), is_recursive))
+            .collect();
+        for (root, is_recursive) in overlapping {
+            let _ = self.add_watch(root, is_recursive, true);
+        }
+
+        // as well as the watches of directories watched for pending paths
+        let helpers: Vec<PathBuf> = self.pending
+            .values()
+            .map(|&(_, ref dir)| dir.clone())
+            .filter(|dir| dir.starts_with(path))
+            .collect();
+        for dir in helpers {
+            let _ = self.add_single_watch(dir, false, false);
+        }
+    }
+
+    fn watch_info(&mut self) -> Vec<WatchInfo> {
+        // forget the failed directories that have been watched or removed in the meantime
+        let watches = &self.watches;
+        self.failed.retain(|dir| !watches.contains_key(dir) && dir.exists());
+
+        self.roots
+            .iter()
+            .map(|(root, &is_recursive)| {
+                let watch_count = if is_recursive {
+                    self.watches.keys().filter(|path| path.starts_with(root)).count()
+                } else if self.watches.contains_key(root) {
+                    1
+                } else {
+                    0
+                };
+                let health = if self.pending.contains_key(root) {
+                    WatchHealth::Pending
+                } else if !self.watches.contains_key(root) {
+                    WatchHealth::Lost
+                } else if is_recursive && self.failed.iter().any(|dir| dir.starts_with(root)) {
+                    WatchHealth::Degraded
+                } else {
+                    WatchHealth::Healthy
+                };
+                WatchInfo {
+                    path: root.clone(),
+                    recursive_mode: RecursiveMode::from_recursive(is_recursive),
+                    watch_count: watch_count,
+                    health: health,
+                }
+            })
+            .collect()
+    }
+
     fn remove_all_watches(&mut self) -> Result<()> {
         if let Some(ref inotify) = self.inotify {
             for w in self.paths.keys() {
-                try!(inotify.rm_watch(*w).map_err(Error::Io));
+                try!(rm_watch(inotify, *w));
             }
             self.watches.clear();
             self.paths.clear();
@@ -338,89 +1123,150 @@ impl INotifyHandler {
     }
 }
 
-impl Watcher for INotifyWatcher {
-    fn new_raw(tx: Sender<RawEvent>) -> Result<INotifyWatcher> {
-        INotify::init()
-            .and_then(|inotify| EventLoop::new().map(|l| (inotify, l)))
-            .and_then(|(inotify, mut event_loop)| {
-                let inotify_fd = inotify.fd;
-                let evented_inotify = mio::unix::EventedFd(&inotify_fd);
-
-                let handler = INotifyHandler {
-                    inotify: Some(inotify),
-                    event_tx: EventTx::Raw { tx: tx },
-                    watches: HashMap::new(),
-                    paths: HashMap::new(),
-                };
+impl INotifyWatcher {
+    fn create(event_tx: EventTx, config: &Config) -> Result<INotifyWatcher> {
+        let inotify = try!(INotify::init().map_err(Error::Io));
+        // a finer timer for the rename timeout, it only runs while a rename is pending
+        let mut loop_config = EventLoopConfig::new();
+        loop_config.timer_tick_ms(10);
+        let mut event_loop = try!(EventLoop::configured(loop_config).map_err(Error::Io));
 
-                event_loop.register(&evented_inotify,
-                              INOTIFY,
-                              mio::EventSet::readable(),
-                              mio::PollOpt::level())
-                    .map(|_| (event_loop, handler))
-            })
-            .map(|(mut event_loop, mut handler)| {
-                let channel = event_loop.channel();
+        let inotify_fd = inotify.fd;
+        let evented_inotify = mio::unix::EventedFd(&inotify_fd);
 
-                ThreadBuilder::new()
-                    .name("INotify Watcher".to_owned())
-                    .spawn(move || event_loop.run(&mut handler))
-                    .unwrap();
+        let mut handler = INotifyHandler {
+            inotify: Some(inotify),
+            event_tx: event_tx,
+            watches: HashMap::new(),
+            paths: HashMap::new(),
+            roots: HashMap::new(),
+            failed: HashSet::new(),
+            pending: HashMap::new(),
+            pending_watches: config.pending_watches().unwrap_or(false),
+            persistent_roots: config.persistent_roots().unwrap_or(false),
+            symlink_policy: config.symlink_policy().unwrap_or_default(),
+            access_events: config.access_events().unwrap_or(false),
+            masks: HashMap::new(),
+            specs: HashMap::new(),
+            rename_event: None,
+            rename_timeout: config.rename_timeout().map_or(RENAME_TIMEOUT_MS, duration_ms),
+            rename_timer: None,
+        };
 
-                INotifyWatcher(channel)
-            })
-            .map_err(Error::Io)
-    }
-
-    fn new(tx: Sender<DebouncedEvent>, delay: Duration) -> Result<INotifyWatcher> {
-        INotify::init()
-            .and_then(|inotify| EventLoop::new().map(|l| (inotify, l)))
-            .and_then(|(inotify, mut event_loop)| {
-                let inotify_fd = inotify.fd;
-                let evented_inotify = mio::unix::EventedFd(&inotify_fd);
-
-                let handler = INotifyHandler {
-                    inotify: Some(inotify),
-                    event_tx: EventTx::Debounced {
-                        tx: tx.clone(),
-                        debounce: Debounce::new(delay, tx),
-                    },
-                    watches: HashMap::new(),
-                    paths: HashMap::new(),
-                };
+        try!(event_loop.register(&evented_inotify,
+                      INOTIFY,
+                      mio::EventSet::readable(),
+                      mio::PollOpt::level())
+            .map_err(Error::Io));
 
-                event_loop.register(&evented_inotify,
-                              INOTIFY,
-                              mio::EventSet::readable(),
-                              mio::PollOpt::level())
-                    .map(|_| (event_loop, handler))
+        let channel = event_loop.channel();
+        let registry = {
+            let channel = Mutex::new(event_loop.channel());
+            WatchRegistry::new(move |_, id| {
+                if let Ok(channel) = channel.lock() {
+                    // nobody is waiting for the result, the event loop may even be gone
+                    let (tx, _) = mpsc::channel();
+                    let _ = channel.send(EventLoopMsg::RemoveHandle(id, tx));
+                }
             })
-            .map(|(mut event_loop, mut handler)| {
-                let channel = event_loop.channel();
+        };
 
-                ThreadBuilder::new()
-                    .name("INotify Watcher".to_owned())
-                    .spawn(move || event_loop.run(&mut handler))
-                    .unwrap();
+        let name = config.thread_name().unwrap_or("INotify Watcher").to_owned();
+        try!(ThreadBuilder::new()
+            .name(name)
+            .spawn(move || event_loop.run(&mut handler))
+            .map_err(Error::Io));
 
-                INotifyWatcher(channel)
-            })
-            .map_err(Error::Io)
+        Ok(INotifyWatcher(channel, registry))
     }
 
-    fn watch<P: AsRef<Path>>(&mut self, path: P, recursive_mode: RecursiveMode) -> Result<()> {
-        let pb = if path.as_ref().is_absolute() {
-            path.as_ref().to_owned()
+    /// Begin watching a path with a custom inotify mask
+    ///
+    /// The mask replaces the flags the watcher would request, for the path and, in recursive mode,
+    /// for all directories below it. Flags like `IN_ONESHOT`, `IN_EXCL_UNLINK`, `IN_ONLYDIR` and
+    /// `IN_DONT_FOLLOW` are passed on to inotify, `IN_MASK_ADD` is managed by the watcher. Only
+    /// the events requested by the mask are reported, which saves space in the inotify queue.
+    /// `IN_ACCESS`, `IN_OPEN` and `IN_CLOSE_NOWRITE` report access events for this watch only,
+    /// `IN_ALL_EVENTS` reports all of them along with the usual events.
+    /// Without `IN_CREATE` and `IN_MOVED_TO`, directories created inside a recursively watched
+    /// path aren't watched.
+    ///
+    /// Once a watch added with `IN_ONESHOT` has reported an event, it has been removed by the
+    /// kernel. This is reported as a `WATCH_REMOVED` event and as `WatchHealth::Lost` by
+    /// `watch_info`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use notify::{INotifyWatcher, RecursiveMode, Watcher};
+    /// # use notify::inotify::flags;
+    /// # use std::sync::mpsc::channel;
+    /// # let (tx, rx) = channel();
+    /// let mut watcher = INotifyWatcher::new_raw(tx).unwrap();
+    ///
+    /// // report the next write to the file only
+    /// let mask = flags::IN_MODIFY | flags::IN_ONESHOT;
+    /// let _watch = watcher.watch_with_mask("/home/test/notify.log", RecursiveMode::NonRecursive, mask)
+    ///     .unwrap();
+    /// ```
+    pub fn watch_with_mask<P: AsRef<Path>>(&mut self,
+                                           path: P,
+                                           recursive_mode: RecursiveMode,
+                                           mask: flags::Mask)
+                                           -> Result<WatchId> {
+        self.add_watch(path.as_ref(), recursive_mode, Filter::new(), Some(mask))
+    }
+
+    fn add_watch(&mut self,
+                 path: &Path,
+                 recursive_mode: RecursiveMode,
+                 filter: Filter,
+                 mask: Option<flags::Mask>)
+                 -> Result<WatchId> {
+        let pb = if path.is_absolute() {
+            path.to_owned()
         } else {
             let p = try!(env::current_dir().map_err(Error::Io));
             p.join(path)
         };
+        let id = self.1.register(pb.clone());
         let (tx, rx) = mpsc::channel();
-        let msg = EventLoopMsg::AddWatch(pb, recursive_mode, tx);
+        let msg = EventLoopMsg::AddWatch(pb, watch_id::id(&id), recursive_mode, filter, mask, tx);
 
         // we expect the event loop to live and reply => unwraps must not panic
         self.0.send(msg).unwrap();
-        rx.recv().unwrap()
+        match rx.recv().unwrap() {
+            Ok(()) => Ok(id),
+            Err(e) => {
+                self.1.discard(id);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Watcher for INotifyWatcher {
+    fn new_raw_with_config<H: EventHandler<RawEvent>>(handler: H,
+                                                      config: Config)
+                                                      -> Result<INotifyWatcher> {
+        try!(config::check_supported(&config, SUPPORTED_OPTIONS, false));
+        INotifyWatcher::create(EventTx::new_raw(handler, &config), &config)
+    }
+
+    fn new_with_config<H: EventHandler<DebouncedEvent>>(handler: H,
+                                                        config: Config)
+                                                        -> Result<INotifyWatcher> {
+        try!(config::check_supported(&config, SUPPORTED_OPTIONS, true));
+        let event_tx = try!(EventTx::new_debounced(handler, &config));
+        INotifyWatcher::create(event_tx, &config)
+    }
+
+    fn watch_filtered<P: AsRef<Path>>(&mut self,
+                                      path: P,
+                                      recursive_mode: RecursiveMode,
+                                      filter: Filter)
+                                      -> Result<WatchId> {
+        self.add_watch(path.as_ref(), recursive_mode, filter, None)
     }
 
     fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
@@ -431,11 +1277,49 @@ impl Watcher for INotifyWatcher {
             p.join(path)
         };
         let (tx, rx) = mpsc::channel();
-        let msg = EventLoopMsg::RemoveWatch(pb, tx);
+        let msg = EventLoopMsg::RemoveWatch(pb.clone(), tx);
 
         // we expect the event loop to live and reply => unwraps must not panic
         self.0.send(msg).unwrap();
-        rx.recv().unwrap()
+        try!(rx.recv().unwrap());
+        self.1.forget(&pb);
+        Ok(())
+    }
+
+    fn watch_info(&self) -> Result<Vec<WatchInfo>> {
+        let (tx, rx) = mpsc::channel();
+
+        // we expect the event loop to live and reply => unwraps must not panic
+        self.0.send(EventLoopMsg::WatchInfo(tx)).unwrap();
+        Ok(rx.recv().unwrap())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        let (tx, rx) = mpsc::channel();
+
+        // the watch tree is still maintained by the event loop, only the delivery is paused
+        // we expect the event loop to live and reply => unwraps must not panic
+        self.0.send(EventLoopMsg::Pause(tx)).unwrap();
+        rx.recv().unwrap();
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        let (tx, rx) = mpsc::channel();
+
+        // we expect the event loop to live and reply => unwraps must not panic
+        self.0.send(EventLoopMsg::Resume(tx)).unwrap();
+        rx.recv().unwrap();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        let (tx, rx) = mpsc::channel();
+
+        // we expect the event loop to live and reply => unwraps must not panic
+        self.0.send(EventLoopMsg::Flush(tx)).unwrap();
+        rx.recv().unwrap();
+        Ok(())
     }
 }
 
@@ -14,8 +14,8 @@
 //! Notify provides two APIs. The default API _debounces_ events (if the backend reports two
 //! similar events in close succession, Notify will only report one). The raw API emits file
 //! changes as soon as they happen. For more details, see
-//! [`Watcher::new_raw`](trait.Watcher.html#tymethod.new_raw) and
-//! [`Watcher::new`](trait.Watcher.html#tymethod.new).
+//! [`Watcher::new_raw`](trait.Watcher.html#method.new_raw) and
+//! [`Watcher::new`](trait.Watcher.html#method.new).
 //!
 //! ## Default (debounced) API
 //!
@@ -36,7 +36,7 @@
 //!
 //!     // Add a path to be watched. All files and directories at that path and
 //!     // below will be monitored for changes.
-//!     watcher.watch("/home/test/notify", RecursiveMode::Recursive).unwrap();
+//!     let _watch = watcher.watch("/home/test/notify", RecursiveMode::Recursive).unwrap();
 //!
 //!     loop {
 //!         match rx.recv() {
@@ -70,11 +70,11 @@
 //!
 //!     // Add a path to be watched. All files and directories at that path and
 //!     // below will be monitored for changes.
-//!     watcher.watch("/home/test/notify", RecursiveMode::Recursive).unwrap();
+//!     let _watch = watcher.watch("/home/test/notify", RecursiveMode::Recursive).unwrap();
 //!
 //!     loop {
 //!         match rx.recv() {
-//!            Ok(RawEvent{path: Some(path), op: Ok(op), cookie}) => {
+//!            Ok(RawEvent{path: Some(path), op: Ok(op), cookie, ..}) => {
 //!                println!("{:?} {:?} ({:?})", op, path, cookie)
 //!            },
 //!            Ok(event) => println!("broken event: {:?}", event),
@@ -86,7 +86,58 @@
 //!
 //! The event structure is described in the [`RawEvent`](struct.RawEvent.html) documentation,
 //! all possible operations delivered in an event are described in the [`op`](op/index.html)
-//! documentation.
+//! documentation. A more detailed description of the operation, e.g. whether a file or a
+//! directory has been created, is available in the [`kind`](event/index.html) of the event.
+//!
+//! ## Event handlers
+//!
+//! Both APIs accept anything implementing [`EventHandler`](trait.EventHandler.html), not only
+//! channels. Closures can be used to handle events directly on the watcher's thread:
+//!
+//! ```no_run
+//! extern crate notify;
+//!
+//! use notify::{Watcher, RecursiveMode, RawEvent, raw_watcher};
+//!
+//! fn main() {
+//!     let mut watcher = raw_watcher(|event: RawEvent| println!("{:?}", event)).unwrap();
+//!
+//!     let _watch = watcher.watch("/home/test/notify", RecursiveMode::Recursive).unwrap();
+//!
+//!     loop {
+//!         std::thread::park();
+//!     }
+//! }
+//! ```
+//!
+//! With the `futures` feature enabled, events can also be received as a `futures::Stream`, see
+//! the [`stream`](stream/index.html) module.
+//!
+//! Wrap a handler in [`Batched`](struct.Batched.html) to receive the events in batches, e.g. all
+//! events of one read from inotify, instead of one by one:
+//!
+//! ```no_run
+//! use notify::{Watcher, RecursiveMode, RawEvent, Batched, raw_watcher};
+//! use std::sync::mpsc::channel;
+//!
+//! let (tx, rx) = channel();
+//! let mut watcher = raw_watcher(Batched::new(tx)).unwrap();
+//! let _watch = watcher.watch("/home/test/notify", RecursiveMode::Recursive).unwrap();
+//!
+//! for events in rx.iter() {
+//!     let events: Vec<RawEvent> = events;
+//!     println!("{} events", events.len());
+//! }
+//! ```
+//!
+//! ## Serialization
+//!
+//! With the `serde` feature enabled, `RawEvent`, `DebouncedEvent`, `Op`, `EventKind`, `Error` and
+//! `WatchInfo` implement `Serialize` and `Deserialize`. `Op` is serialized as the list of the
+//! names of its flags, e.g. `["CREATE", "WRITE"]`. `Error` is serialized as a struct with the
+//! fields `kind` (e.g. `"io"`), `message` and `errno`, the OS error code of I/O errors.
+//! Unsupported option errors carry the name of the option in an additional `option` field, errors
+//! concerning a path carry it in an additional `path` field.
 
 #![deny(missing_docs)]
 
@@ -100,8 +151,22 @@ extern crate fsevent_sys;
 extern crate winapi;
 extern crate libc;
 extern crate filetime;
+extern crate glob;
+#[cfg(feature = "ignore")]
+extern crate ignore;
+#[cfg(feature = "futures")]
+extern crate futures;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
 
 pub use self::op::Op;
+pub use self::event::EventKind;
+pub use self::config::Config;
+pub use self::filter::Filter;
+pub use self::symlink::SymlinkPolicy;
+pub use self::watch_id::WatchId;
+pub use self::watch_info::{WatchInfo, WatchHealth};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::Sender;
@@ -119,6 +184,7 @@ pub use self::inotify::INotifyWatcher;
 pub use self::windows::ReadDirectoryChangesWatcher;
 pub use self::null::NullWatcher;
 pub use self::poll::PollWatcher;
+pub use self::fallback::{FallbackWatcher, Backend};
 
 #[cfg(target_os="linux")]
 pub mod inotify;
@@ -127,10 +193,21 @@ pub mod fsevent;
 #[cfg(target_os="windows")]
 pub mod windows;
 
+pub mod event;
 pub mod null;
 pub mod poll;
+pub mod fallback;
+#[cfg(feature = "futures")]
+pub mod stream;
 
+mod config;
 mod debounce;
+mod filter;
+#[cfg(feature = "serde")]
+mod serialize;
+mod symlink;
+mod watch_id;
+mod watch_info;
 
 /// Contains the `Op` type which describes the actions for an event.
 ///
@@ -159,14 +236,33 @@ mod debounce;
 /// __This event is only available on Linux__.
 ///
 ///
+/// # Access, Open and Close-Nowrite
+///
+/// An `ACCESS` event is emitted whenever a file has been read, an `OPEN` event whenever a file or
+/// directory has been opened and a `CLOSE_NOWRITE` event whenever a file or directory that was
+/// opened for reading only has been closed. Directories are opened when their contents are listed,
+/// which includes the watcher scanning directories that have been created inside a recursive watch.
+///
+/// These events are only emitted if they have been enabled, for the whole watcher with
+/// [`Config::with_access_events`](struct.Config.html#method.with_access_events) or for single
+/// watches with a mask including them, see
+/// [`INotifyWatcher::watch_with_mask`](struct.INotifyWatcher.html#method.watch_with_mask).
+///
+/// __These events are only available on Linux__.
+///
+///
 /// # Create
 ///
 /// A `CREATE` event is emitted whenever a new file or directory is created.
 ///
-/// Upon receiving a `Create` event for a directory, it is necessary to scan the newly created
-/// directory for contents. The directory can contain files or directories if those contents were
-/// created before the directory could be watched, or if the directory was moved into the watched
-/// directory.
+/// A directory can already contain files or directories when it is reported, if those contents
+/// were created before the directory could be watched, or if the directory was moved into the
+/// watched directory.
+///
+/// On Linux, a `CREATE` event is emitted for each of these contents after the event of the
+/// directory, in a recursive watch. They may be reported twice if they are created while the
+/// directory is being scanned. On other platforms, it is necessary to scan the newly created
+/// directory for contents upon receiving a `CREATE` event for it.
 ///
 /// # Remove
 ///
@@ -182,7 +278,10 @@ mod debounce;
 /// __Linux__
 ///
 /// When a watched file or directory is removed, its watch gets destroyed and no new events will be
-/// sent.
+/// sent. This is reported as a `WATCH_REMOVED` event after the `REMOVE` event.
+///
+/// With [`Config::with_persistent_roots`](struct.Config.html#method.with_persistent_roots) the
+/// path is watched again once it has been recreated, which is reported as a `CREATE` event.
 ///
 /// __Windows__
 ///
@@ -220,6 +319,13 @@ mod debounce;
 /// A rename that originates outside of a watched directory and ends inside of a watched directory
 /// produces a `CREATE` event.
 ///
+/// On Linux, the events of a directory renamed within a recursively watched directory carry its
+/// new path afterwards.
+///
+/// On Linux, the source path is held back until the destination has been read, at most for the
+/// timeout set with [`Config::with_rename_timeout`](struct.Config.html#method.with_rename_timeout).
+/// A rename whose destination isn't read in time is reported as a `DELETE` event.
+///
 /// __OS X__
 ///
 /// A `RENAME` event is produced whenever a file or directory is moved. This includes moves within
@@ -242,7 +348,8 @@ mod debounce;
 /// __Linux__
 ///
 /// Linux will continue to watch the moved file or directory. Events will contain paths prefixed
-/// with the old path.
+/// with the old path, unless the move has been reported by a watched directory as well, in which
+/// case the watched path itself is renamed and has to be unwatched with its new path.
 ///
 /// __Windows__
 ///
@@ -291,6 +398,23 @@ mod debounce;
 /// of the paths.
 ///
 ///
+/// # Watch-Removed and Unmounted
+///
+/// A `WATCH_REMOVED` event is emitted for a watched path whenever the system has removed its
+/// watch, because the path has been removed, its file system has been unmounted or a oneshot watch
+/// has fired. Its changes are no longer reported and
+/// [`Watcher::watch_info`](trait.Watcher.html#tymethod.watch_info) reports it as `Lost`.
+///
+/// An `UNMOUNTED` event is emitted for a watched path whenever the file system containing it has
+/// been unmounted, followed by a `WATCH_REMOVED` event.
+///
+/// Watches of directories inside recursively watched paths are cleaned up silently.
+///
+/// __Linux__
+///
+/// Only emitted on Linux, for inotify's `IN_IGNORED` and `IN_UNMOUNT` events.
+///
+///
 /// # Write
 ///
 /// A `WRITE` event is emitted whenever a file has been written to.
@@ -320,12 +444,23 @@ pub mod op {
             const CLOSE_WRITE = 0b0100000,
     /// Directories need to be rescanned
             const RESCAN      = 0b1000000,
+    /// Read from
+            const ACCESS      = 0b10000000,
+    /// Opened
+            const OPEN        = 0b100000000,
+    /// File opened for reading only was closed
+            const CLOSE_NOWRITE = 0b1000000000,
+    /// Watch removed by the system
+            const WATCH_REMOVED = 0b10000000000,
+    /// File system unmounted
+            const UNMOUNTED   = 0b100000000000,
         }
     }
 }
 
 /// Event delivered when action occurs on a watched path in _raw_ mode
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RawEvent {
     /// Path where the event originated.
     ///
@@ -345,6 +480,13 @@ pub struct RawEvent {
     /// there is an error reading from inotify.
     pub op: Result<Op>,
 
+    /// Detailed kind of the operation.
+    ///
+    /// Converts to `op` with `Op::from(kind)`, unless the kind is `EventKind::Any`. This is the
+    /// case if `op` is `Err` or if the backend reported several actions at once. For details have
+    /// a look at the [notify::event](event/index.html) documentation.
+    pub kind: EventKind,
+
     /// Unique cookie associating related events (for `RENAME` events).
     ///
     /// If two consecutive `RENAME` events share the same cookie, it means that the first event
@@ -354,11 +496,21 @@ pub struct RawEvent {
     /// For details on handling `RENAME` events with the `FsEventWatcher` have a look at the
     /// [notify::op](op/index.html) documentation.
     pub cookie: Option<u32>,
+
+    /// Watched path containing `path` and `path` relative to it.
+    ///
+    /// Only set if the watcher has been created with
+    /// [`Config::with_relative_paths`](struct.Config.html#method.with_relative_paths). If the
+    /// path lies inside several watched paths, the innermost one is used. The relative path is
+    /// empty for events of the watched path itself.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub relative: Option<(PathBuf, PathBuf)>,
 }
 
 unsafe impl Send for RawEvent {}
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// Event delivered when action occurs on a watched path in debounced mode
 pub enum DebouncedEvent {
     /// `NoticeWrite` is emitted imediatelly after the first write event for the path.
@@ -385,10 +537,11 @@ pub enum DebouncedEvent {
     /// `Write` events have a higher priority than `Chmod`. `Chmod` will not be emitted if it's
     /// detected before the `Write` event has been emitted.
     ///
-    /// Upon receiving a `Create` event for a directory, it is necessary to scan the newly created
-    /// directory for contents. The directory can contain files or directories if those contents
-    /// were created before the directory could be watched, or if the directory was moved into the
-    /// watched directory.
+    /// A newly created directory can contain files or directories if those contents were created
+    /// before the directory could be watched, or if the directory was moved into the watched
+    /// directory. On Linux, the watcher scans the directory and emits a `Create` event for each of
+    /// these contents, in a recursive watch. On other platforms, it is necessary to scan the newly
+    /// created directory for contents upon receiving a `Create` event for it.
     Write(PathBuf),
 
     /// `Chmod` is emitted when attributes have been changed and no events were detected for the
@@ -409,6 +562,27 @@ pub enum DebouncedEvent {
     /// to re-scan the watched directories.
     Rescan,
 
+    /// `Access` is emitted imediatelly after a file has been read.
+    ///
+    /// Only emitted if access events have been enabled, like `Open` and `CloseNowrite`. Access
+    /// events don't change the path, so they aren't debounced.
+    Access(PathBuf),
+
+    /// `Open` is emitted imediatelly after a file or directory has been opened.
+    Open(PathBuf),
+
+    /// `CloseNowrite` is emitted imediatelly after a file or directory that was opened for
+    /// reading only has been closed.
+    CloseNowrite(PathBuf),
+
+    /// `WatchRemoved` is emitted imediatelly after the system has removed the watch of a watched
+    /// path, e.g. because it has been deleted. Its changes are no longer reported.
+    WatchRemoved(PathBuf),
+
+    /// `Unmounted` is emitted imediatelly after the file system containing a watched path has
+    /// been unmounted. It is followed by `WatchRemoved`.
+    Unmounted(PathBuf),
+
     /// `Error` is emitted imediatelly after a error has been detected.
     ///
     ///  This event may contain a path for which the error was detected.
@@ -423,7 +597,12 @@ impl PartialEq for DebouncedEvent {
             (&DebouncedEvent::Create(ref a), &DebouncedEvent::Create(ref b)) |
             (&DebouncedEvent::Write(ref a), &DebouncedEvent::Write(ref b)) |
             (&DebouncedEvent::Chmod(ref a), &DebouncedEvent::Chmod(ref b)) |
-            (&DebouncedEvent::Remove(ref a), &DebouncedEvent::Remove(ref b)) => a == b,
+            (&DebouncedEvent::Remove(ref a), &DebouncedEvent::Remove(ref b)) |
+            (&DebouncedEvent::Access(ref a), &DebouncedEvent::Access(ref b)) |
+            (&DebouncedEvent::Open(ref a), &DebouncedEvent::Open(ref b)) |
+            (&DebouncedEvent::CloseNowrite(ref a), &DebouncedEvent::CloseNowrite(ref b)) |
+            (&DebouncedEvent::WatchRemoved(ref a), &DebouncedEvent::WatchRemoved(ref b)) |
+            (&DebouncedEvent::Unmounted(ref a), &DebouncedEvent::Unmounted(ref b)) => a == b,
             (&DebouncedEvent::Rename(ref a1, ref a2), &DebouncedEvent::Rename(ref b1, ref b2)) => {
                 (a1 == b1 && a2 == b2)
             }
@@ -444,21 +623,71 @@ pub enum Error {
     /// I/O errors
     Io(io::Error),
 
-    /// The provided path does not exist
-    PathNotFound,
+    /// The path does not exist
+    PathNotFound(PathBuf),
 
     /// Attempted to remove a watch that does not exist
     WatchNotFound,
+
+    /// An option of the [`Config`](struct.Config.html) isn't supported by the watcher
+    ///
+    /// Contains the name of the option.
+    UnsupportedOption(&'static str),
+
+    /// The limit of watches of the OS has been reached while watching the path
+    ///
+    /// On Linux the limit can be raised with the `fs.inotify.max_user_watches` sysctl.
+    MaxFilesWatch(PathBuf),
+
+    /// Access to the path was denied
+    PermissionDenied(PathBuf),
+
+    /// A directory was expected at the path
+    NotADirectory(PathBuf),
+}
+
+impl Error {
+    /// Wrap an I/O error that occurred for `path`, using a more specific variant if there is one
+    fn for_path(err: io::Error, path: &Path) -> Error {
+        if err.kind() == io::ErrorKind::NotFound {
+            return Error::PathNotFound(path.to_owned());
+        }
+        if err.kind() == io::ErrorKind::PermissionDenied {
+            return Error::PermissionDenied(path.to_owned());
+        }
+
+        #[cfg(unix)]
+        {
+            if err.raw_os_error() == Some(libc::ENOTDIR) {
+                return Error::NotADirectory(path.to_owned());
+            }
+        }
+
+        Error::Io(err)
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let error = String::from(match *self {
-            Error::PathNotFound => "No path was found.",
-            Error::WatchNotFound => "No watch was found.",
-            Error::Generic(ref err) => err.as_ref(),
-            Error::Io(ref err) => err.description(),
-        });
+        let error = match *self {
+            Error::PathNotFound(ref path) => format!("Path not found: {}", path.display()),
+            Error::WatchNotFound => "No watch was found.".to_owned(),
+            Error::UnsupportedOption(option) => format!("Unsupported option: {}", option),
+            Error::MaxFilesWatch(ref path) => {
+                format!("Maximum number of watches reached while watching {}",
+                        path.display())
+            }
+            Error::PermissionDenied(ref path) => format!("Permission denied: {}", path.display()),
+            Error::NotADirectory(ref path) => format!("Not a directory: {}", path.display()),
+            Error::Generic(ref err) => err.clone(),
+            // wrapped errors are described by their source
+            Error::Io(_) => {
+                match self.source() {
+                    Some(source) => source.to_string(),
+                    None => String::new(),
+                }
+            }
+        };
 
         write!(f, "{}", error)
     }
@@ -468,25 +697,85 @@ impl fmt::Display for Error {
 pub type Result<T> = StdResult<T, Error>;
 
 impl StdError for Error {
-    fn description(&self) -> &str {
+    fn source(&self) -> Option<&(StdError + 'static)> {
         match *self {
-            Error::PathNotFound => "No path was found",
-            Error::WatchNotFound => "No watch was found",
-            Error::Generic(_) => "Generic error",
-            Error::Io(_) => "I/O Error",
+            Error::Io(ref source) => Some(source),
+            _ => None,
         }
     }
+}
 
-    fn cause(&self) -> Option<&StdError> {
-        match *self {
-            Error::Io(ref cause) => Some(cause),
-            _ => None,
+/// Type that can handle the events delivered by a watcher
+///
+/// `EventHandler` is implemented for `std::sync::mpsc::Sender` and for closures, so events can be
+/// received through a channel or handled directly. Implement it for your own types to deliver
+/// events to custom queues, loggers or actors without an additional forwarding thread.
+///
+/// Handlers are called on the watcher's internal threads, they should not block for long.
+pub trait EventHandler<E>: Send + 'static {
+    /// Handle an event.
+    fn handle_event(&mut self, event: E);
+
+    /// Handle the events that a watcher received at once, e.g. with one read from the kernel.
+    ///
+    /// Calls `handle_event` for each of them by default.
+    fn handle_events(&mut self, events: Vec<E>) {
+        for event in events {
+            self.handle_event(event);
+        }
+    }
+}
+
+impl<E, F> EventHandler<E> for F
+    where F: FnMut(E) + Send + 'static
+{
+    fn handle_event(&mut self, event: E) {
+        (self)(event);
+    }
+}
+
+impl<E: Send + 'static> EventHandler<E> for Sender<E> {
+    fn handle_event(&mut self, event: E) {
+        // the receiver may have been dropped, there's nobody left to report to
+        let _ = self.send(event);
+    }
+}
+
+/// Event handler delivering events in batches
+///
+/// Wraps a handler of `Vec<E>`, which receives all events a watcher received at once in a single
+/// call:
+///
+/// - the raw events of one read from inotify, FSEvents or `ReadDirectoryChangesW`, or of one scan
+///   of the `PollWatcher`
+/// - the debounced events whose delay ran out at the same time
+///
+/// Events that are reported on their own, like notices in debounced mode, are delivered as a batch
+/// of one event. Empty batches are never delivered.
+pub struct Batched<H>(H);
+
+impl<H> Batched<H> {
+    /// Wrap a handler of event batches
+    pub fn new(handler: H) -> Batched<H> {
+        Batched(handler)
+    }
+}
+
+impl<E: Send + 'static, H: EventHandler<Vec<E>>> EventHandler<E> for Batched<H> {
+    fn handle_event(&mut self, event: E) {
+        self.0.handle_event(vec![event]);
+    }
+
+    fn handle_events(&mut self, events: Vec<E>) {
+        if !events.is_empty() {
+            self.0.handle_event(events);
         }
     }
 }
 
 /// Indicates whether only the provided directory or its sub-directories as well should be watched
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum RecursiveMode {
     /// Watch all sub-directories as well, including directories created after installing the watch
     Recursive,
@@ -502,6 +791,14 @@ impl RecursiveMode {
             RecursiveMode::NonRecursive => false,
         }
     }
+
+    fn from_recursive(is_recursive: bool) -> RecursiveMode {
+        if is_recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        }
+    }
 }
 
 /// Type that can deliver file activity notifications
@@ -512,12 +809,26 @@ impl RecursiveMode {
 pub trait Watcher: Sized {
     /// Create a new watcher in _raw_ mode.
     ///
-    /// Events will be sent using the provided `tx` immediately after they occurred.
-    fn new_raw(tx: Sender<RawEvent>) -> Result<Self>;
+    /// Events will be delivered to the provided `handler` immediately after they occurred. The
+    /// handler may be a `Sender<RawEvent>`, a closure or any other type implementing
+    /// [`EventHandler`](trait.EventHandler.html).
+    fn new_raw<H: EventHandler<RawEvent>>(handler: H) -> Result<Self> {
+        Self::new_raw_with_config(handler, Config::new())
+    }
+
+    /// Create a new watcher in _raw_ mode using the options set in `config`.
+    ///
+    /// See [`Watcher::new_raw`](#method.new_raw).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnsupportedOption` if `config` sets an option that isn't supported by the
+    /// watcher or in raw mode, like the debounce delay.
+    fn new_raw_with_config<H: EventHandler<RawEvent>>(handler: H, config: Config) -> Result<Self>;
 
     /// Create a new _debounced_ watcher with a `delay`.
     ///
-    /// Events won't be sent immediately but after the specified delay.
+    /// Events won't be delivered to the `handler` immediately but after the specified delay.
     ///
     /// # Advantages
     ///
@@ -546,7 +857,21 @@ pub trait Watcher: Sized {
     ///
     /// If a file is saved very slowly, you might receive a `Write` event even though the file is
     /// still being written to.
-    fn new(tx: Sender<DebouncedEvent>, delay: Duration) -> Result<Self>;
+    fn new<H: EventHandler<DebouncedEvent>>(handler: H, delay: Duration) -> Result<Self> {
+        Self::new_with_config(handler, Config::new().with_debounce_delay(delay))
+    }
+
+    /// Create a new _debounced_ watcher using the options set in `config`.
+    ///
+    /// The debounce delay has to be set with
+    /// [`Config::with_debounce_delay`](struct.Config.html#method.with_debounce_delay). See
+    /// [`Watcher::new`](#method.new).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnsupportedOption` if `config` sets an option that isn't supported by the
+    /// watcher.
+    fn new_with_config<H: EventHandler<DebouncedEvent>>(handler: H, config: Config) -> Result<Self>;
 
     /// Begin watching a new path.
     ///
@@ -556,15 +881,99 @@ pub trait Watcher: Sized {
     ///
     /// If the `path` is a file, `recursive_mode` will be ignored and events will be delivered only
     /// for the file.
-    fn watch<P: AsRef<Path>>(&mut self, path: P, recursive_mode: RecursiveMode) -> Result<()>;
+    ///
+    /// Paths that don't exist yet can only be watched if the watcher has been created with
+    /// [`Config::with_pending_watches`](struct.Config.html#method.with_pending_watches).
+    ///
+    /// Returns a handle of the watch. The path is watched until the handle, and the handles of
+    /// all other watches of the same path, have been dropped. See [`WatchId`](struct.WatchId.html).
+    fn watch<P: AsRef<Path>>(&mut self, path: P, recursive_mode: RecursiveMode) -> Result<WatchId> {
+        self.watch_filtered(path, recursive_mode, Filter::new())
+    }
+
+    /// Begin watching a new path, delivering only events for paths accepted by `filter`.
+    ///
+    /// See [`Watcher::watch`](#method.watch) and [`Filter`](struct.Filter.html). If the path is
+    /// already watched, every handle keeps its own filter and recursive mode: events are
+    /// delivered if any of the handles accepts them, until the handle is dropped.
+    fn watch_filtered<P: AsRef<Path>>(&mut self,
+                                      path: P,
+                                      recursive_mode: RecursiveMode,
+                                      filter: Filter)
+                                      -> Result<WatchId>;
 
     /// Stop watching a path.
     ///
+    /// The watch is removed regardless of its handles, the remaining handles have no effect
+    /// afterwards.
+    ///
     /// # Errors
     ///
     /// Returns an error in the case that `path` has not been watched or if removing the watch
     /// fails.
     fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<()>;
+
+    /// Give up the handle of a watch, removing the watch if it was the last handle of its path.
+    ///
+    /// Same as dropping the handle, but reports errors while removing the watch. The handle must
+    /// have been returned by this watcher.
+    fn unwatch_id(&mut self, id: WatchId) -> Result<()> {
+        match watch_id::release(id) {
+            Some(path) => self.unwatch(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Describe the watched paths: their recursive mode, the number of watches they use and
+    /// whether their changes are reported.
+    ///
+    /// See [`WatchInfo`](struct.WatchInfo.html) for the platform specific details.
+    fn watch_info(&self) -> Result<Vec<WatchInfo>>;
+
+    /// List the watched paths.
+    fn watched_paths(&self) -> Result<Vec<PathBuf>> {
+        Ok(try!(self.watch_info()).into_iter().map(|info| info.path).collect())
+    }
+
+    /// Stop delivering events without removing any watches.
+    ///
+    /// The watches are kept up to date while the watcher is paused, e.g. new directories inside
+    /// recursively watched paths are still watched, but their events are discarded. In debounced
+    /// mode, this includes the events that are still being debounced when the watcher is paused.
+    fn pause(&mut self) -> Result<()>;
+
+    /// Deliver events again after the watcher has been paused.
+    ///
+    /// If events have been discarded while the watcher was paused, a `RESCAN` event is emitted
+    /// (`DebouncedEvent::Rescan` in debounced mode), the watched paths should be rescanned to find
+    /// out what has changed.
+    fn resume(&mut self) -> Result<()>;
+
+    /// Deliver the events of all changes made before the call.
+    ///
+    /// Blocks until the events have been passed to the event handler. In debounced mode, the
+    /// pending events are delivered right away instead of after the debounce delay.
+    ///
+    /// # Platform support
+    ///
+    /// __Linux__
+    ///
+    /// All events queued by inotify are read.
+    ///
+    /// __OS X__
+    ///
+    /// The FSEvents stream is flushed synchronously.
+    ///
+    /// __Windows__
+    ///
+    /// The completed directory reads are handled. Changes the system hasn't reported yet are not
+    /// flushed.
+    ///
+    /// __Polling__
+    ///
+    /// The watched paths are scanned immediately. Writes within the same second as the previous
+    /// scan aren't detected, because the modification times only have a resolution of a second.
+    fn flush(&mut self) -> Result<()>;
 }
 
 /// The recommended `Watcher` implementation for the current platform
@@ -582,17 +991,19 @@ pub type RecommendedWatcher = PollWatcher;
 
 /// Convenience method for creating the `RecommendedWatcher` for the current platform in _raw_ mode.
 ///
-/// See [`Watcher::new_raw`](trait.Watcher.html#tymethod.new_raw).
-pub fn raw_watcher(tx: Sender<RawEvent>) -> Result<RecommendedWatcher> {
-    Watcher::new_raw(tx)
+/// See [`Watcher::new_raw`](trait.Watcher.html#method.new_raw).
+pub fn raw_watcher<H: EventHandler<RawEvent>>(handler: H) -> Result<RecommendedWatcher> {
+    Watcher::new_raw(handler)
 }
 
 /// Convenience method for creating the `RecommendedWatcher` for the current
 /// platform in default (debounced) mode.
 ///
-/// See [`Watcher::new`](trait.Watcher.html#tymethod.new).
-pub fn watcher(tx: Sender<DebouncedEvent>, delay: Duration) -> Result<RecommendedWatcher> {
-    Watcher::new(tx, delay)
+/// See [`Watcher::new`](trait.Watcher.html#method.new).
+pub fn watcher<H: EventHandler<DebouncedEvent>>(handler: H,
+                                                 delay: Duration)
+                                                 -> Result<RecommendedWatcher> {
+    Watcher::new(handler, delay)
 }
 
 
@@ -607,3 +1018,23 @@ fn display_formatted_errors() {
                format!("{}",
                        Error::Io(io::Error::new(io::ErrorKind::Other, expected))));
 }
+
+#[test]
+fn display_formatted_path_errors() {
+    let path = PathBuf::from("/home/test/notify");
+
+    assert_eq!("Path not found: /home/test/notify",
+               format!("{}", Error::PathNotFound(path.clone())));
+    assert_eq!("Permission denied: /home/test/notify",
+               format!("{}", Error::PermissionDenied(path.clone())));
+    assert_eq!("Not a directory: /home/test/notify",
+               format!("{}", Error::NotADirectory(path.clone())));
+}
+
+#[test]
+fn error_source() {
+    let error = Error::Io(io::Error::new(io::ErrorKind::Other, "Some error"));
+    assert_eq!("Some error", error.source().expect("missing source").to_string());
+
+    assert!(Error::MaxFilesWatch(PathBuf::from("/home/test/notify")).source().is_none());
+}
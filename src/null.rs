@@ -2,30 +2,56 @@
 
 #![allow(unused_variables)]
 
-use std::sync::mpsc::Sender;
 use std::path::Path;
-use std::time::Duration;
-use super::{RawEvent, DebouncedEvent, Result, Watcher, RecursiveMode};
+use super::{Config, Filter, RawEvent, DebouncedEvent, EventHandler, Result, Watcher, RecursiveMode,
+            WatchId, WatchInfo};
+use super::watch_id::WatchRegistry;
 
 /// Stub `Watcher` implementation
 ///
-/// Events are never delivered from this watcher.
+/// Events are never delivered from this watcher. All configuration options are accepted and
+/// ignored.
 pub struct NullWatcher;
 
 impl Watcher for NullWatcher {
-    fn new_raw(tx: Sender<RawEvent>) -> Result<NullWatcher> {
+    fn new_raw_with_config<H: EventHandler<RawEvent>>(handler: H,
+                                                      config: Config)
+                                                      -> Result<NullWatcher> {
         Ok(NullWatcher)
     }
 
-    fn new(tx: Sender<DebouncedEvent>, delay: Duration) -> Result<NullWatcher> {
+    fn new_with_config<H: EventHandler<DebouncedEvent>>(handler: H,
+                                                        config: Config)
+                                                        -> Result<NullWatcher> {
         Ok(NullWatcher)
     }
 
-    fn watch<P: AsRef<Path>>(&mut self, path: P, recursive_mode: RecursiveMode) -> Result<()> {
-        Ok(())
+    fn watch_filtered<P: AsRef<Path>>(&mut self,
+                                      path: P,
+                                      recursive_mode: RecursiveMode,
+                                      filter: Filter)
+                                      -> Result<WatchId> {
+        // there's nothing to remove once the handle is dropped
+        Ok(WatchRegistry::new(|_, _| ()).register(path.as_ref().to_owned()))
     }
 
     fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         Ok(())
     }
+
+    fn watch_info(&self) -> Result<Vec<WatchInfo>> {
+        Ok(Vec::new())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
@@ -4,19 +4,21 @@
 //! Rust stdlib APIs and should work on all of the platforms it supports.
 
 use filetime::FileTime;
-use self::walkdir::WalkDir;
 use std::collections::HashMap;
-use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock, Mutex};
-use std::sync::mpsc::Sender;
 use std::thread;
 use std::time::Duration;
-use super::{Error, RawEvent, DebouncedEvent, op, Result, Watcher, RecursiveMode};
-use super::debounce::{Debounce, EventTx};
+use super::{Config, Error, Filter, RawEvent, DebouncedEvent, EventHandler, EventKind, op, Result,
+            Watcher, RecursiveMode, SymlinkPolicy, WatchId, WatchInfo, WatchHealth};
+use super::event::{ModifyKind, RemoveKind};
+use super::config;
+use super::debounce::EventTx;
+use super::symlink;
+use super::watch_id::{self, WatchRegistry};
 
 extern crate time;
-extern crate walkdir;
 
 struct PathData {
     mtime: u64,
@@ -25,34 +27,304 @@ struct PathData {
 
 struct WatchData {
     is_recursive: bool,
+    /// Whether the watched path didn't exist (yet or anymore) when it was last scanned
+    pending: bool,
     paths: HashMap<PathBuf, PathData>,
 }
 
+/// Handles that have been dropped, but not removed from their watches yet
+type Released = Mutex<Vec<(PathBuf, u64)>>;
+
+/// Remove a watch and its filter, returns false if the path isn't watched
+fn remove_watch(watches: &mut HashMap<PathBuf, WatchData>,
+                event_tx: &mut EventTx,
+                path: &Path)
+                -> bool {
+    if watches.remove(path).is_some() {
+        event_tx.filters_mut().remove(path);
+        true
+    } else {
+        false
+    }
+}
+
+/// Remove the handles that have been dropped, a watch is removed along with its last handle
+///
+/// Handles may be dropped by the event handler while the watches are locked to deliver events, so
+/// they're removed by the next one to lock them.
+fn remove_released(released: &Released,
+                   watches: &mut HashMap<PathBuf, WatchData>,
+                   event_tx: &mut EventTx,
+                   symlink_policy: SymlinkPolicy) {
+    let handles = match released.lock() {
+        Ok(mut released) => released.drain(..).collect::<Vec<_>>(),
+        Err(_) => return,
+    };
+    for (path, id) in handles {
+        if event_tx.filters_mut().remove_handle(&path, id) {
+            if let Some(data) = watches.get_mut(&path) {
+                update_watch(&path, data, event_tx, symlink_policy);
+            }
+        } else {
+            watches.remove(&path);
+        }
+    }
+}
+
+/// Apply the recursive mode and filters of the current handles of a watch to its entries
+///
+/// Entries that are no longer inside the watch are dropped and new ones are added without
+/// reporting them. Entries that have been removed since the last scan are kept, so that the next
+/// scan reports them.
+fn update_watch(watch: &Path,
+                data: &mut WatchData,
+                event_tx: &mut EventTx,
+                symlink_policy: SymlinkPolicy) {
+    data.is_recursive = event_tx.filters().is_recursive(watch);
+    match symlink::metadata(watch, symlink_policy) {
+        Ok(ref metadata) if metadata.is_dir() => (),
+        _ => return,
+    }
+
+    let current_time = time::precise_time_s();
+    let depth = if data.is_recursive { usize::max_value() } else { 1 };
+    let mut paths = HashMap::new();
+    for entry in symlink::walk(watch, watch, depth, symlink_policy, event_tx.filters()) {
+        if let Ok(m) = entry.metadata {
+            let path_data = data.paths.remove(&entry.path).unwrap_or_else(|| {
+                PathData {
+                    mtime: FileTime::from_last_modification_time(&m).seconds(),
+                    last_check: current_time,
+                }
+            });
+            paths.insert(entry.path, path_data);
+        }
+    }
+    for (path, path_data) in data.paths.drain() {
+        if symlink::metadata(&path, symlink_policy).is_err() {
+            paths.insert(path, path_data);
+        }
+    }
+    data.paths = paths;
+}
+
+/// Scan the watched paths and report the changes since the last scan
+fn scan(watches: &mut HashMap<PathBuf, WatchData>,
+        event_tx: &mut EventTx,
+        symlink_policy: SymlinkPolicy,
+        persistent_roots: bool) {
+    let current_time = time::precise_time_s();
+    // the changes found by one scan are delivered as one batch
+    let mut events = Vec::new();
+
+    for (watch, &mut WatchData { is_recursive, ref mut pending, ref mut paths }) in
+        watches.iter_mut() {
+        match symlink::metadata(watch, symlink_policy) {
+            Err(_) if *pending => continue,
+            Err(ref e) if persistent_roots && e.kind() == io::ErrorKind::NotFound => {
+                // its entries are reported as removed below
+                *pending = true;
+                continue;
+            }
+            Err(e) => {
+                events.push(RawEvent {
+                    path: Some(watch.clone()),
+                    op: Err(Error::for_path(e, watch)),
+                    kind: EventKind::Any,
+                    cookie: None,
+                    relative: None,
+                });
+                continue;
+            }
+            Ok(metadata) => {
+                // the path of a pending watch has been created, it is reported like any new path
+                *pending = false;
+
+                if !metadata.is_dir() {
+                    let mtime = FileTime::from_last_modification_time(&metadata).seconds();
+                    match paths.insert(watch.clone(),
+                                       PathData {
+                                           mtime: mtime,
+                                           last_check: current_time,
+                                       }) {
+                        None => {
+                            events.push(RawEvent {
+                                path: Some(watch.clone()),
+                                op: Ok(op::CREATE),
+                                kind: EventKind::create(false),
+                                cookie: None,
+                                relative: None,
+                            });
+                        }
+                        Some(PathData { mtime: old_mtime, .. }) => {
+                            if mtime > old_mtime {
+                                events.push(RawEvent {
+                                    path: Some(watch.clone()),
+                                    op: Ok(op::WRITE),
+                                    kind: EventKind::Modify(ModifyKind::Data),
+                                    cookie: None,
+                                    relative: None,
+                                });
+                            }
+                        }
+                    }
+                } else {
+                    let depth = if is_recursive { usize::max_value() } else { 1 };
+                    for entry in symlink::walk(watch,
+                                               watch,
+                                               depth,
+                                               symlink_policy,
+                                               event_tx.filters()) {
+                        let path = &entry.path;
+
+                        match entry.metadata {
+                            Err(e) => {
+                                events.push(RawEvent {
+                                    path: Some(path.to_path_buf()),
+                                    op: Err(Error::for_path(e, path)),
+                                    kind: EventKind::Any,
+                                    cookie: None,
+                                    relative: None,
+                                });
+                            }
+                            Ok(m) => {
+                                let mtime = FileTime::from_last_modification_time(&m).seconds();
+                                match paths.insert(path.to_path_buf(),
+                                                   PathData {
+                                                       mtime: mtime,
+                                                       last_check: current_time,
+                                                   }) {
+                                    None => {
+                                        events.push(RawEvent {
+                                            path: Some(path.to_path_buf()),
+                                            op: Ok(op::CREATE),
+                                            kind: EventKind::create(m.is_dir()),
+                                            cookie: None,
+                                            relative: None,
+                                        });
+                                    }
+                                    Some(PathData { mtime: old_mtime, .. }) => {
+                                        // in debounced mode, changes to the contents of a
+                                        // directory are reported for the entries only, like
+                                        // with the other backends
+                                        if mtime > old_mtime &&
+                                           !(m.is_dir() && event_tx.is_debounced()) {
+                                            events.push(RawEvent {
+                                                path: Some(path.to_path_buf()),
+                                                op: Ok(op::WRITE),
+                                                kind: EventKind::Modify(ModifyKind::Data),
+                                                cookie: None,
+                                                relative: None,
+                                            });
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for (_, &mut WatchData { ref mut paths, .. }) in watches.iter_mut() {
+        let mut removed = Vec::new();
+        for (path, &PathData { last_check, .. }) in paths.iter() {
+            if last_check < current_time {
+                events.push(RawEvent {
+                    path: Some(path.clone()),
+                    op: Ok(op::REMOVE),
+                    kind: EventKind::Remove(RemoveKind::Any),
+                    cookie: None,
+                    relative: None,
+                });
+                removed.push(path.clone());
+            }
+        }
+        for path in removed {
+            (*paths).remove(&path);
+        }
+    }
+
+    event_tx.send_all(events);
+}
+
 /// Polling based `Watcher` implementation
 pub struct PollWatcher {
-    event_tx: EventTx,
+    event_tx: Arc<Mutex<EventTx>>,
     watches: Arc<Mutex<HashMap<PathBuf, WatchData>>>,
     open: Arc<RwLock<bool>>,
+    symlink_policy: SymlinkPolicy,
+    pending_watches: bool,
+    persistent_roots: bool,
+    registry: WatchRegistry,
+    released: Arc<Released>,
 }
 
+/// Options supported by the `PollWatcher`
+const SUPPORTED_OPTIONS: &'static [&'static str] = &["pending_watches",
+                                                     "persistent_roots",
+                                                     "poll_interval",
+                                                     "symlink_policy",
+                                                     "relative_paths",
+                                                     "thread_name"];
+
 impl PollWatcher {
     /// Create a PollWatcher which polls every `delay` milliseconds
-    pub fn with_delay_ms(tx: Sender<RawEvent>, delay: u32) -> Result<PollWatcher> {
+    pub fn with_delay_ms<H: EventHandler<RawEvent>>(handler: H, delay: u32) -> Result<PollWatcher> {
+        let config = Config::new().with_poll_interval(Duration::from_millis(delay as u64));
+        PollWatcher::new_raw_with_config(handler, config)
+    }
+
+    fn create(event_tx: EventTx, config: &Config, interval: Duration) -> Result<PollWatcher> {
+        let event_tx = Arc::new(Mutex::new(event_tx));
+        let watches = Arc::new(Mutex::new(HashMap::new()));
+        let symlink_policy = config.symlink_policy().unwrap_or_default();
+        let released = Arc::new(Mutex::new(Vec::new()));
+        let registry = {
+            let event_tx = event_tx.clone();
+            let watches = watches.clone();
+            let released = released.clone();
+            WatchRegistry::new(move |path, id| {
+                if let Ok(mut released) = released.lock() {
+                    released.push((path.to_owned(), id));
+                }
+                // never blocks, the handler may drop a handle while the watches are locked
+                if let (Ok(mut watches), Ok(mut event_tx)) = (watches.try_lock(),
+                                                              event_tx.try_lock()) {
+                    remove_released(&released, &mut watches, &mut event_tx, symlink_policy);
+                }
+            })
+        };
+
         let mut p = PollWatcher {
-            event_tx: EventTx::Raw { tx: tx.clone() },
-            watches: Arc::new(Mutex::new(HashMap::new())),
+            event_tx: event_tx,
+            watches: watches,
             open: Arc::new(RwLock::new(true)),
+            symlink_policy: symlink_policy,
+            pending_watches: config.pending_watches().unwrap_or(false),
+            persistent_roots: config.persistent_roots().unwrap_or(false),
+            registry: registry,
+            released: released,
         };
-        let event_tx = EventTx::Raw { tx: tx };
-        p.run(Duration::from_millis(delay as u64), event_tx);
+        try!(p.run(config.poll_interval().unwrap_or(interval), config.thread_name()));
         Ok(p)
     }
 
-    fn run(&mut self, delay: Duration, mut event_tx: EventTx) {
+    fn run(&mut self, delay: Duration, thread_name: Option<&str>) -> Result<()> {
         let watches = self.watches.clone();
         let open = self.open.clone();
+        let event_tx = self.event_tx.clone();
+        let released = self.released.clone();
+        let symlink_policy = self.symlink_policy;
+        let persistent_roots = self.persistent_roots;
+
+        let mut builder = thread::Builder::new();
+        if let Some(name) = thread_name {
+            builder = builder.name(name.to_owned());
+        }
 
-        thread::spawn(move || {
+        try!(builder.spawn(move || {
             // In order of priority:
             // TODO: handle chmod events
             // TODO: handle renames
@@ -63,149 +335,76 @@ impl PollWatcher {
                     break;
                 }
 
-                if let Ok(mut watches) = watches.lock() {
-                    let current_time = time::precise_time_s();
-
-                    for (watch, &mut WatchData { is_recursive, ref mut paths }) in
-                        watches.iter_mut() {
-                        match fs::metadata(watch) {
-                            Err(e) => {
-                                event_tx.send(RawEvent {
-                                    path: Some(watch.clone()),
-                                    op: Err(Error::Io(e)),
-                                    cookie: None,
-                                });
-                                continue;
-                            }
-                            Ok(metadata) => {
-                                if !metadata.is_dir() {
-                                    let mtime = FileTime::from_last_modification_time(&metadata)
-                                        .seconds();
-                                    match paths.insert(watch.clone(),
-                                                       PathData {
-                                                           mtime: mtime,
-                                                           last_check: current_time,
-                                                       }) {
-                                        None => {
-                                            unreachable!();
-                                        }
-                                        Some(PathData { mtime: old_mtime, .. }) => {
-                                            if mtime > old_mtime {
-                                                event_tx.send(RawEvent {
-                                                    path: Some(watch.clone()),
-                                                    op: Ok(op::WRITE),
-                                                    cookie: None,
-                                                });
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    let depth = if is_recursive { usize::max_value() } else { 1 };
-                                    for entry in WalkDir::new(watch)
-                                        .follow_links(true)
-                                        .max_depth(depth)
-                                        .into_iter()
-                                        .filter_map(|e| e.ok()) {
-                                        let path = entry.path();
-
-                                        match entry.metadata() {
-                                            Err(e) => {
-                                                event_tx.send(RawEvent {
-                                                    path: Some(path.to_path_buf()),
-                                                    op: Err(Error::Io(e.into())),
-                                                    cookie: None,
-                                                });
-                                            }
-                                            Ok(m) => {
-                                                let mtime =
-                                                    FileTime::from_last_modification_time(&m)
-                                                        .seconds();
-                                                match paths.insert(path.to_path_buf(),
-                                                                   PathData {
-                                                                       mtime: mtime,
-                                                                       last_check: current_time,
-                                                                   }) {
-                                                    None => {
-                                                        event_tx.send(RawEvent {
-                                                            path: Some(path.to_path_buf()),
-                                                            op: Ok(op::CREATE),
-                                                            cookie: None,
-                                                        });
-                                                    }
-                                                    Some(PathData { mtime: old_mtime, .. }) => {
-                                                        if mtime > old_mtime {
-                                                            event_tx.send(RawEvent {
-                                                                path: Some(path.to_path_buf()),
-                                                                op: Ok(op::WRITE),
-                                                                cookie: None,
-                                                            });
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-
-                    for (_, &mut WatchData { ref mut paths, .. }) in watches.iter_mut() {
-                        let mut removed = Vec::new();
-                        for (path, &PathData { last_check, .. }) in paths.iter() {
-                            if last_check < current_time {
-                                event_tx.send(RawEvent {
-                                    path: Some(path.clone()),
-                                    op: Ok(op::REMOVE),
-                                    cookie: None,
-                                });
-                                removed.push(path.clone());
-                            }
-                        }
-                        for path in removed {
-                            (*paths).remove(&path);
-                        }
-                    }
-
-                    thread::sleep(delay);
+                if let (Ok(mut watches), Ok(mut event_tx)) = (watches.lock(), event_tx.lock()) {
+                    remove_released(&released, &mut watches, &mut event_tx, symlink_policy);
+                    scan(&mut watches, &mut event_tx, symlink_policy, persistent_roots);
+                    // the handles dropped by the handler during the scan
+                    remove_released(&released, &mut watches, &mut event_tx, symlink_policy);
                 }
+
+                // sleep without holding the locks, otherwise `watch` and `unwatch` would block
+                // for up to `delay`
+                thread::sleep(delay);
             }
-        });
+        }).map_err(Error::Io));
+
+        Ok(())
     }
 }
 
 impl Watcher for PollWatcher {
-    fn new_raw(tx: Sender<RawEvent>) -> Result<PollWatcher> {
-        PollWatcher::with_delay_ms(tx, 30_000)
+    fn new_raw_with_config<H: EventHandler<RawEvent>>(handler: H,
+                                                      config: Config)
+                                                      -> Result<PollWatcher> {
+        try!(config::check_supported(&config, SUPPORTED_OPTIONS, false));
+        PollWatcher::create(EventTx::new_raw(handler, &config),
+                            &config,
+                            Duration::from_secs(30))
     }
 
-    fn new(tx: Sender<DebouncedEvent>, delay: Duration) -> Result<PollWatcher> {
-        let mut p = PollWatcher {
-            event_tx: EventTx::DebouncedTx { tx: tx.clone() },
-            watches: Arc::new(Mutex::new(HashMap::new())),
-            open: Arc::new(RwLock::new(true)),
-        };
-        let event_tx = EventTx::Debounced {
-            tx: tx.clone(),
-            debounce: Debounce::new(delay, tx),
-        };
-        p.run(delay, event_tx);
-        Ok(p)
+    fn new_with_config<H: EventHandler<DebouncedEvent>>(handler: H,
+                                                        config: Config)
+                                                        -> Result<PollWatcher> {
+        try!(config::check_supported(&config, SUPPORTED_OPTIONS, true));
+        let event_tx = try!(EventTx::new_debounced(handler, &config));
+        // `new_debounced` fails without a delay
+        let delay = config.debounce_delay().unwrap();
+        PollWatcher::create(event_tx, &config, delay)
     }
 
-    fn watch<P: AsRef<Path>>(&mut self, path: P, recursive_mode: RecursiveMode) -> Result<()> {
-        if let Ok(mut watches) = self.watches.lock() {
+    fn watch_filtered<P: AsRef<Path>>(&mut self,
+                                      path: P,
+                                      recursive_mode: RecursiveMode,
+                                      filter: Filter)
+                                      -> Result<WatchId> {
+        let watch = path.as_ref().to_owned();
+        let id = self.registry.register(watch.clone());
+        let is_recursive = recursive_mode.is_recursive();
+
+        if let (Ok(mut watches), Ok(mut event_tx)) = (self.watches.lock(), self.event_tx.lock()) {
             let current_time = time::precise_time_s();
+            remove_released(&self.released, &mut watches, &mut event_tx, self.symlink_policy);
 
-            let watch = path.as_ref().to_owned();
+            event_tx.filters_mut().insert(watch.clone(), watch_id::id(&id), filter, is_recursive);
+            if let Some(data) = watches.get_mut(&watch) {
+                // another handle of a watched path
+                update_watch(&watch, data, &mut event_tx, self.symlink_policy);
+                return Ok(id);
+            }
 
-            match fs::metadata(path) {
+            match symlink::metadata(&watch, self.symlink_policy) {
+                Err(ref e) if self.pending_watches && e.kind() == io::ErrorKind::NotFound => {
+                    watches.insert(watch,
+                                   WatchData {
+                                       is_recursive: is_recursive,
+                                       pending: true,
+                                       paths: HashMap::new(),
+                                   });
+                }
                 Err(e) => {
-                    self.event_tx.send(RawEvent {
-                        path: Some(watch.clone()),
-                        op: Err(Error::Io(e)),
-                        cookie: None,
-                    });
+                    event_tx.filters_mut().remove(&watch);
+                    self.registry.discard(id);
+                    return Err(Error::for_path(e, &watch));
                 }
                 Ok(metadata) => {
                     if !metadata.is_dir() {
@@ -218,29 +417,28 @@ impl Watcher for PollWatcher {
                                      });
                         watches.insert(watch,
                                        WatchData {
-                                           is_recursive: recursive_mode.is_recursive(),
+                                           is_recursive: is_recursive,
+                                           pending: false,
                                            paths: paths,
                                        });
                     } else {
                         let mut paths = HashMap::new();
-                        let depth = if recursive_mode.is_recursive() {
-                            usize::max_value()
-                        } else {
-                            1
-                        };
-                        for entry in WalkDir::new(watch.clone())
-                            .follow_links(true)
-                            .max_depth(depth)
-                            .into_iter()
-                            .filter_map(|e| e.ok()) {
-                            let path = entry.path();
-
-                            match entry.metadata() {
+                        let depth = if is_recursive { usize::max_value() } else { 1 };
+                        for entry in symlink::walk(&watch,
+                                                   &watch,
+                                                   depth,
+                                                   self.symlink_policy,
+                                                   event_tx.filters()) {
+                            let path = &entry.path;
+
+                            match entry.metadata {
                                 Err(e) => {
-                                    self.event_tx.send(RawEvent {
+                                    event_tx.send(RawEvent {
                                         path: Some(path.to_path_buf()),
-                                        op: Err(Error::Io(e.into())),
+                                        op: Err(Error::for_path(e, path)),
+                                        kind: EventKind::Any,
                                         cookie: None,
+                                        relative: None,
                                     });
                                 }
                                 Ok(m) => {
@@ -255,23 +453,80 @@ impl Watcher for PollWatcher {
                         }
                         watches.insert(watch,
                                        WatchData {
-                                           is_recursive: recursive_mode.is_recursive(),
+                                           is_recursive: is_recursive,
+                                           pending: false,
                                            paths: paths,
                                        });
                     }
                 }
             }
         }
-        Ok(())
+        Ok(id)
     }
 
     fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
-        if (*self.watches).lock().unwrap().remove(path.as_ref()).is_some() {
+        let removed = match (self.watches.lock(), self.event_tx.lock()) {
+            (Ok(mut watches), Ok(mut event_tx)) => {
+                remove_released(&self.released, &mut watches, &mut event_tx, self.symlink_policy);
+                remove_watch(&mut watches, &mut event_tx, path.as_ref())
+            }
+            _ => false,
+        };
+        if removed {
+            self.registry.forget(path.as_ref());
             Ok(())
         } else {
             Err(Error::WatchNotFound)
         }
     }
+
+    fn watch_info(&self) -> Result<Vec<WatchInfo>> {
+        let mut watches = (*self.watches).lock().unwrap();
+        if let Ok(mut event_tx) = self.event_tx.lock() {
+            remove_released(&self.released, &mut watches, &mut event_tx, self.symlink_policy);
+        }
+        Ok(watches.iter()
+            .map(|(watch, &WatchData { is_recursive, pending, ref paths })| {
+                WatchInfo {
+                    path: watch.clone(),
+                    recursive_mode: RecursiveMode::from_recursive(is_recursive),
+                    watch_count: paths.len(),
+                    // the entries of a watched path are dropped once it can't be read anymore
+                    health: if pending {
+                        WatchHealth::Pending
+                    } else if paths.contains_key(watch) {
+                        WatchHealth::Healthy
+                    } else {
+                        WatchHealth::Lost
+                    },
+                }
+            })
+            .collect())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        // the paths are still scanned, so no changes are reported as the watcher resumes
+        (*self.event_tx).lock().unwrap().pause();
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        (*self.event_tx).lock().unwrap().resume();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let (Ok(mut watches), Ok(mut event_tx)) = (self.watches.lock(), self.event_tx.lock()) {
+            remove_released(&self.released, &mut watches, &mut event_tx, self.symlink_policy);
+            scan(&mut watches,
+                 &mut event_tx,
+                 self.symlink_policy,
+                 self.persistent_roots);
+            event_tx.flush();
+            remove_released(&self.released, &mut watches, &mut event_tx, self.symlink_policy);
+        }
+        Ok(())
+    }
 }
 
 impl Drop for PollWatcher {
@@ -0,0 +1,128 @@
+//! Serialization of events and errors with serde
+//!
+//! `Op` is serialized as the list of the names of its flags, e.g. `["CREATE", "WRITE"]`.
+//!
+//! `Error` is serialized as a struct with the fields `kind`, `message` and `errno`. `kind` is one
+//! of `generic`, `io`, `path_not_found`, `watch_not_found`, `unsupported_option`,
+//! `max_files_watch`, `permission_denied` or `not_a_directory`, `errno` is the OS error code of
+//! I/O errors, if there is one. Unsupported option errors carry the name of the option in an
+//! additional `option` field, errors concerning a path carry it in an additional `path` field.
+//! I/O errors are deserialized from their OS error code if it is set, otherwise from their
+//! message.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Error as DeError;
+use std::io;
+use std::path::PathBuf;
+use std::result::Result as StdResult;
+use super::{op, Op, Error};
+use super::config;
+
+/// Names of the `Op` flags
+const OP_NAMES: &'static [(&'static str, Op)] = &[("CHMOD", op::CHMOD),
+                                                  ("CREATE", op::CREATE),
+                                                  ("REMOVE", op::REMOVE),
+                                                  ("RENAME", op::RENAME),
+                                                  ("WRITE", op::WRITE),
+                                                  ("CLOSE_WRITE", op::CLOSE_WRITE),
+                                                  ("RESCAN", op::RESCAN),
+                                                  ("ACCESS", op::ACCESS),
+                                                  ("OPEN", op::OPEN),
+                                                  ("CLOSE_NOWRITE", op::CLOSE_NOWRITE),
+                                                  ("WATCH_REMOVED", op::WATCH_REMOVED),
+                                                  ("UNMOUNTED", op::UNMOUNTED)];
+
+impl Serialize for Op {
+    fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+        let names: Vec<&str> = OP_NAMES.iter()
+            .filter(|&&(_, flag)| self.contains(flag))
+            .map(|&(name, _)| name)
+            .collect();
+        names.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Op {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Op, D::Error> {
+        let names: Vec<String> = try!(Deserialize::deserialize(deserializer));
+        let mut o = Op::empty();
+        for name in names {
+            match OP_NAMES.iter().find(|&&(n, _)| n == name) {
+                Some(&(_, flag)) => o.insert(flag),
+                None => return Err(D::Error::custom(format!("unknown operation: {}", name))),
+            }
+        }
+        Ok(o)
+    }
+}
+
+/// Serialized form of an `Error`
+#[derive(Serialize, Deserialize)]
+struct ErrorRepr {
+    kind: String,
+    message: String,
+    errno: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    option: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+        let (kind, errno, option, path) = match *self {
+            Error::Generic(_) => ("generic", None, None, None),
+            Error::Io(ref err) => ("io", err.raw_os_error(), None, None),
+            Error::PathNotFound(ref path) => ("path_not_found", None, None, Some(path)),
+            Error::WatchNotFound => ("watch_not_found", None, None, None),
+            Error::UnsupportedOption(option) => ("unsupported_option", None, Some(option), None),
+            Error::MaxFilesWatch(ref path) => ("max_files_watch", None, None, Some(path)),
+            Error::PermissionDenied(ref path) => ("permission_denied", None, None, Some(path)),
+            Error::NotADirectory(ref path) => ("not_a_directory", None, None, Some(path)),
+        };
+        let message = match *self {
+            Error::Io(ref err) => err.to_string(),
+            _ => self.to_string(),
+        };
+
+        ErrorRepr {
+                kind: kind.to_owned(),
+                message: message,
+                errno: errno,
+                option: option.map(|option| option.to_owned()),
+                path: path.cloned(),
+            }
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Error {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Error, D::Error> {
+        let repr: ErrorRepr = try!(Deserialize::deserialize(deserializer));
+        match repr.kind.as_ref() {
+            "generic" => Ok(Error::Generic(repr.message)),
+            "io" => {
+                Ok(Error::Io(match repr.errno {
+                    Some(errno) => io::Error::from_raw_os_error(errno),
+                    None => io::Error::new(io::ErrorKind::Other, repr.message),
+                }))
+            }
+            "path_not_found" => Ok(Error::PathNotFound(try!(required_path(repr.path)))),
+            "watch_not_found" => Ok(Error::WatchNotFound),
+            "unsupported_option" => {
+                match repr.option.as_ref().and_then(|option| config::option_name(option)) {
+                    Some(option) => Ok(Error::UnsupportedOption(option)),
+                    None => Err(D::Error::custom(format!("unknown option: {:?}", repr.option))),
+                }
+            }
+            "max_files_watch" => Ok(Error::MaxFilesWatch(try!(required_path(repr.path)))),
+            "permission_denied" => Ok(Error::PermissionDenied(try!(required_path(repr.path)))),
+            "not_a_directory" => Ok(Error::NotADirectory(try!(required_path(repr.path)))),
+            kind => Err(D::Error::custom(format!("unknown error kind: {}", kind))),
+        }
+    }
+}
+
+fn required_path<E: DeError>(path: Option<PathBuf>) -> StdResult<PathBuf, E> {
+    path.ok_or_else(|| E::missing_field("path"))
+}
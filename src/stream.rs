@@ -0,0 +1,165 @@
+//! Asynchronous event delivery using `futures::Stream`
+//!
+//! Requires the `futures` feature. [`channel`](fn.channel.html) creates a bounded queue: the
+//! [`EventSender`](struct.EventSender.html) half is passed to a watcher as its event handler, the
+//! [`EventStream`](struct.EventStream.html) half is polled by the async code receiving the
+//! events. The stream is woken up directly from the watcher's threads (e.g. the inotify event
+//! loop or the debouncing timer), no additional forwarding thread is needed.
+//!
+//! # Backpressure
+//!
+//! When the queue is full, the watcher thread delivering the event blocks until the stream has
+//! been polled. While blocked, the watcher can't process any other events or commands: calling
+//! `watch()` or `unwatch()` on the same watcher will block as well. With inotify the kernel keeps
+//! queueing events in the meantime, if its queue overflows a `RESCAN` event will be emitted.
+//!
+//! # Cancellation
+//!
+//! Dropping the `EventStream` discards all queued events and unblocks the watcher, events
+//! delivered afterwards are dropped immediately. The stream ends once the watcher has been
+//! dropped and all remaining events have been received.
+//!
+//! # Example
+//!
+//! ```no_run
+//! extern crate futures;
+//! extern crate notify;
+//!
+//! use futures::executor::block_on_stream;
+//! use notify::{Watcher, RecursiveMode, raw_watcher};
+//!
+//! fn main() {
+//!     let (tx, events) = notify::stream::channel(1024);
+//!
+//!     let mut watcher = raw_watcher(tx).unwrap();
+//!     let _watch = watcher.watch("/home/test/notify", RecursiveMode::Recursive).unwrap();
+//!
+//!     for event in block_on_stream(events) {
+//!         println!("{:?}", event);
+//!     }
+//! }
+//! ```
+
+use futures::stream::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use super::EventHandler;
+
+struct State<E> {
+    queue: VecDeque<E>,
+    capacity: usize,
+    waker: Option<Waker>,
+    sender_alive: bool,
+    stream_alive: bool,
+}
+
+struct Shared<E> {
+    state: Mutex<State<E>>,
+    not_full: Condvar,
+}
+
+/// Create a bounded channel delivering events to an [`EventStream`](struct.EventStream.html)
+///
+/// At most `capacity` events are buffered, see the [module documentation](index.html) for the
+/// backpressure behavior.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn channel<E: Send + 'static>(capacity: usize) -> (EventSender<E>, EventStream<E>) {
+    assert!(capacity > 0, "capacity must be greater than zero");
+
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::with_capacity(capacity),
+            capacity: capacity,
+            waker: None,
+            sender_alive: true,
+            stream_alive: true,
+        }),
+        not_full: Condvar::new(),
+    });
+
+    (EventSender { shared: shared.clone() }, EventStream { shared: shared })
+}
+
+/// Sending half of a [`channel`](fn.channel.html), to be used as the event handler of a watcher
+pub struct EventSender<E> {
+    shared: Arc<Shared<E>>,
+}
+
+impl<E: Send + 'static> EventHandler<E> for EventSender<E> {
+    fn handle_event(&mut self, event: E) {
+        if let Ok(mut state) = self.shared.state.lock() {
+            while state.stream_alive && state.queue.len() >= state.capacity {
+                state = match self.shared.not_full.wait(state) {
+                    Ok(state) => state,
+                    Err(_) => return,
+                };
+            }
+
+            // nobody is listening anymore
+            if !state.stream_alive {
+                return;
+            }
+
+            state.queue.push_back(event);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<E> Drop for EventSender<E> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.sender_alive = false;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Receiving half of a [`channel`](fn.channel.html)
+///
+/// Yields the events in the order they have been delivered by the watcher.
+pub struct EventStream<E> {
+    shared: Arc<Shared<E>>,
+}
+
+impl<E> Stream for EventStream<E> {
+    type Item = E;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<E>> {
+        let mut state = match self.shared.state.lock() {
+            Ok(state) => state,
+            Err(_) => return Poll::Ready(None),
+        };
+
+        if let Some(event) = state.queue.pop_front() {
+            self.shared.not_full.notify_one();
+            return Poll::Ready(Some(event));
+        }
+
+        if !state.sender_alive {
+            return Poll::Ready(None);
+        }
+
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<E> Drop for EventStream<E> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.stream_alive = false;
+            state.queue.clear();
+        }
+        self.shared.not_full.notify_all();
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use super::filter::WatchFilters;
+
+/// Handling of symbolic links inside recursively watched directories
+///
+/// Set with [`Config::with_symlink_policy`](struct.Config.html#method.with_symlink_policy).
+/// Supported by the `INotifyWatcher` and the `PollWatcher`, defaults to `Follow`.
+///
+/// Links that are followed are watched under the path of the link. A link is never followed if
+/// its target is a directory that is already watched through another path, identified by its
+/// device and inode number. This prevents loops as well as duplicate watches of the same
+/// directory. Directories reached without following any links take precedence.
+///
+/// Links that aren't followed are reported like files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SymlinkPolicy {
+    /// Follow all links
+    Follow,
+
+    /// Don't follow links inside watched directories, a watched path that is a link is still
+    /// resolved
+    NoFollow,
+
+    /// Follow links whose target lies inside the watched path, links leading out of it aren't
+    /// followed
+    StayInsideRoot,
+
+    /// Don't follow any links, a watched path that is a link is watched itself instead of its
+    /// target
+    ///
+    /// The `INotifyWatcher` adds such watches with `IN_DONT_FOLLOW`.
+    WatchLink,
+}
+
+impl Default for SymlinkPolicy {
+    fn default() -> SymlinkPolicy {
+        SymlinkPolicy::Follow
+    }
+}
+
+/// File or directory found while walking a watched path
+pub struct Entry {
+    pub path: PathBuf,
+    /// Metadata of the link target if the entry is a followed link, of the entry itself otherwise
+    pub metadata: io::Result<fs::Metadata>,
+}
+
+/// Identity of a directory, used to detect directories reached through several paths
+#[cfg(unix)]
+type FileId = (u64, u64);
+
+#[cfg(not(unix))]
+type FileId = PathBuf;
+
+#[cfg(unix)]
+fn file_id(_path: &Path, metadata: &fs::Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(path: &Path, _metadata: &fs::Metadata) -> Option<FileId> {
+    fs::canonicalize(path).ok()
+}
+
+struct Walk<'a> {
+    policy: SymlinkPolicy,
+    /// Canonicalized watched path, for `StayInsideRoot`
+    root: Option<PathBuf>,
+    filters: &'a WatchFilters,
+    visited: HashSet<FileId>,
+    /// Links found inside the walked directories, with the depth left below them
+    links: Vec<(PathBuf, usize)>,
+    entries: Vec<Entry>,
+}
+
+impl<'a> Walk<'a> {
+    /// Mark a directory as visited, returns false if it has been visited before
+    fn visit(&mut self, path: &Path, metadata: &fs::Metadata) -> bool {
+        match file_id(path, metadata) {
+            Some(id) => self.visited.insert(id),
+            None => true,
+        }
+    }
+
+    /// Walk the contents of a directory up to `depth` levels deep, without following links
+    fn dir(&mut self, dir: &Path, depth: usize) {
+        if depth == 0 {
+            return;
+        }
+
+        let children = match fs::read_dir(dir) {
+            Ok(children) => children,
+            Err(_) => return,
+        };
+
+        for child in children.filter_map(|child| child.ok()) {
+            let path = child.path();
+            if self.filters.is_excluded(&path) {
+                continue;
+            }
+
+            match fs::symlink_metadata(&path) {
+                Ok(ref metadata) if metadata.file_type().is_symlink() => {
+                    self.links.push((path, depth - 1));
+                }
+                Ok(metadata) => {
+                    let descend = metadata.is_dir() && self.visit(&path, &metadata);
+                    self.entries.push(Entry {
+                        path: path.clone(),
+                        metadata: Ok(metadata),
+                    });
+                    if descend {
+                        self.dir(&path, depth - 1);
+                    }
+                }
+                Err(e) => {
+                    self.entries.push(Entry {
+                        path: path,
+                        metadata: Err(e),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Whether the policy allows following a link
+    fn follows(&self, link: &Path) -> bool {
+        match self.policy {
+            SymlinkPolicy::Follow => true,
+            SymlinkPolicy::NoFollow | SymlinkPolicy::WatchLink => false,
+            SymlinkPolicy::StayInsideRoot => {
+                match (fs::canonicalize(link), self.root.as_ref()) {
+                    (Ok(target), Some(root)) => target.starts_with(root),
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    /// Add a link found inside a directory, walking its target if it is followed
+    fn link(&mut self, link: PathBuf, depth: usize) {
+        let target = if self.follows(&link) {
+            fs::metadata(&link).ok()
+        } else {
+            None
+        };
+
+        match target {
+            Some(ref metadata) if !metadata.is_dir() => {
+                self.entries.push(Entry {
+                    path: link,
+                    metadata: Ok(metadata.clone()),
+                });
+            }
+            Some(metadata) => {
+                if self.visit(&link, &metadata) {
+                    self.entries.push(Entry {
+                        path: link.clone(),
+                        metadata: Ok(metadata),
+                    });
+                    self.dir(&link, depth);
+                } else {
+                    // a loop or a directory watched through another path
+                    self.entries.push(Entry {
+                        metadata: fs::symlink_metadata(&link),
+                        path: link,
+                    });
+                }
+            }
+            None => {
+                self.entries.push(Entry {
+                    metadata: fs::symlink_metadata(&link),
+                    path: link,
+                });
+            }
+        }
+    }
+}
+
+/// Metadata of a watched path, of the link itself if it is a link watched with `WatchLink`
+pub fn metadata(path: &Path, policy: SymlinkPolicy) -> io::Result<fs::Metadata> {
+    if policy == SymlinkPolicy::WatchLink {
+        fs::symlink_metadata(path)
+    } else {
+        fs::metadata(path)
+    }
+}
+
+/// Walk `path` up to `max_depth` levels deep according to `policy`, skipping excluded paths
+///
+/// `root` is the watched path containing `path`. The first entry is `path` itself.
+pub fn walk(path: &Path,
+            root: &Path,
+            max_depth: usize,
+            policy: SymlinkPolicy,
+            filters: &WatchFilters)
+            -> Vec<Entry> {
+    let mut walk = Walk {
+        policy: policy,
+        root: if policy == SymlinkPolicy::StayInsideRoot {
+            fs::canonicalize(root).ok()
+        } else {
+            None
+        },
+        filters: filters,
+        visited: HashSet::new(),
+        links: Vec::new(),
+        entries: Vec::new(),
+    };
+
+    let metadata = metadata(path, policy);
+    let descend = match metadata {
+        Ok(ref metadata) => metadata.is_dir() && walk.visit(path, metadata),
+        Err(_) => false,
+    };
+    walk.entries.push(Entry {
+        path: path.to_path_buf(),
+        metadata: metadata,
+    });
+    if descend {
+        walk.dir(path, max_depth);
+    }
+
+    // links are followed last, so that directories reached without following links are
+    // watched under their own path
+    let mut i = 0;
+    while i < walk.links.len() {
+        let (link, depth) = walk.links[i].clone();
+        walk.link(link, depth);
+        i += 1;
+    }
+
+    walk.entries
+}
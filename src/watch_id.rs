@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+
+/// Handle of a watch
+///
+/// Returned by [`Watcher::watch`](trait.Watcher.html#method.watch). A path stays watched as long
+/// as there is a handle for it: if the same path is watched several times, every call returns a
+/// separate handle and the watch is only removed once all of them have been dropped or passed to
+/// [`Watcher::unwatch_id`](trait.Watcher.html#method.unwatch_id). Every handle keeps the filter
+/// and recursive mode it has been created with, they no longer apply once it has been dropped.
+/// Clones of a handle refer to the same watch.
+///
+/// [`Watcher::unwatch`](trait.Watcher.html#tymethod.unwatch) removes the watch of a path
+/// regardless of its handles, the remaining handles have no effect afterwards.
+///
+/// On OS X the watch of a dropped handle is only removed on the next call to `watch` or `unwatch`,
+/// because the event stream has to be restarted.
+///
+/// # Example
+///
+/// ```no_run
+/// # use notify::{Watcher, RecursiveMode, raw_watcher};
+/// # use std::sync::mpsc::channel;
+/// # let (tx, rx) = channel();
+/// let mut watcher = raw_watcher(tx).unwrap();
+///
+/// let first = watcher.watch("/home/test/notify", RecursiveMode::Recursive).unwrap();
+/// let second = watcher.watch("/home/test/notify", RecursiveMode::Recursive).unwrap();
+///
+/// // still watched through `second`
+/// drop(first);
+///
+/// // removes the watch
+/// watcher.unwatch_id(second).unwrap();
+/// ```
+#[must_use = "the watch is removed when its handle is dropped"]
+#[derive(Clone)]
+pub struct WatchId(Arc<Handle>);
+
+impl WatchId {
+    /// The watched path, as passed to the backend
+    pub fn path(&self) -> &Path {
+        &self.0.path
+    }
+}
+
+impl fmt::Debug for WatchId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WatchId")
+            .field("id", &self.0.id)
+            .field("path", &self.0.path)
+            .finish()
+    }
+}
+
+impl PartialEq for WatchId {
+    fn eq(&self, other: &WatchId) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for WatchId {}
+
+struct Handle {
+    id: u64,
+    path: PathBuf,
+    registry: Weak<Mutex<Registry>>,
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        let registry = match self.registry.upgrade() {
+            Some(registry) => registry,
+            // the watcher is gone
+            None => return,
+        };
+
+        // don't hold the lock while updating the watch, it may drop other handles
+        let unwatch = match registry.lock() {
+            Ok(mut registry) => {
+                match registry.remove(self.id) {
+                    Some(_) => Some(registry.unwatch.clone()),
+                    None => None,
+                }
+            }
+            Err(_) => None,
+        };
+
+        if let Some(unwatch) = unwatch {
+            unwatch(&self.path, self.id);
+        }
+    }
+}
+
+struct Registry {
+    next_id: u64,
+    handles: HashMap<u64, PathBuf>,
+    unwatch: Arc<Fn(&Path, u64) + Send + Sync>,
+}
+
+impl Registry {
+    /// Remove a handle, returns whether it was the last handle of its path
+    ///
+    /// Returns `None` if the handle has been removed already.
+    fn remove(&mut self, id: u64) -> Option<bool> {
+        match self.handles.remove(&id) {
+            Some(path) => Some(!self.handles.values().any(|p| *p == path)),
+            None => None,
+        }
+    }
+}
+
+/// Handles of the watches of a watcher
+pub struct WatchRegistry(Arc<Mutex<Registry>>);
+
+impl WatchRegistry {
+    /// Create a registry calling `unwatch` with the path and id of every dropped handle
+    ///
+    /// The watch of the path has to be removed once all of its handles are gone, until then only
+    /// the options of the dropped handle no longer apply.
+    pub fn new<F>(unwatch: F) -> WatchRegistry
+        where F: Fn(&Path, u64) + Send + Sync + 'static
+    {
+        WatchRegistry(Arc::new(Mutex::new(Registry {
+            next_id: 0,
+            handles: HashMap::new(),
+            unwatch: Arc::new(unwatch),
+        })))
+    }
+
+    /// Create a new handle for `path`
+    pub fn register(&self, path: PathBuf) -> WatchId {
+        let mut registry = self.0.lock().unwrap();
+        let id = registry.next_id;
+        registry.next_id += 1;
+        registry.handles.insert(id, path.clone());
+
+        WatchId(Arc::new(Handle {
+            id: id,
+            path: path,
+            registry: Arc::downgrade(&self.0),
+        }))
+    }
+
+    /// Give up a new handle of a watch that couldn't be added, without calling `unwatch`
+    pub fn discard(&self, id: WatchId) {
+        if let Ok(mut registry) = self.0.lock() {
+            registry.handles.remove(&id.0.id);
+        }
+    }
+
+    /// Invalidate all handles of `path`, its watch has been removed
+    pub fn forget(&self, path: &Path) {
+        if let Ok(mut registry) = self.0.lock() {
+            registry.handles.retain(|_, p| p != path);
+        }
+    }
+}
+
+/// The id of a handle, passed to `unwatch` once the handle is dropped
+pub fn id(id: &WatchId) -> u64 {
+    id.0.id
+}
+
+/// Give up a handle, returns its path if it was the last handle
+///
+/// The caller is responsible for removing the watch of the returned path. The other handles of
+/// the path are updated like on drop.
+pub fn release(id: WatchId) -> Option<PathBuf> {
+    let handle = match Arc::try_unwrap(id.0) {
+        Ok(handle) => handle,
+        // other clones keep the watch alive
+        Err(_) => return None,
+    };
+
+    let registry = match handle.registry.upgrade() {
+        Some(registry) => registry,
+        None => return None,
+    };
+
+    let (last, unwatch) = match registry.lock() {
+        Ok(mut registry) => (registry.remove(handle.id), registry.unwatch.clone()),
+        Err(_) => return None,
+    };
+
+    // the handle has been removed from the registry, dropping it has no effect
+    match last {
+        Some(true) => Some(handle.path.clone()),
+        Some(false) => {
+            unwatch(&handle.path, handle.id);
+            None
+        }
+        None => None,
+    }
+}
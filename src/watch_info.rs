@@ -0,0 +1,61 @@
+use std::path::PathBuf;
+use super::RecursiveMode;
+
+/// Description of a watched path
+///
+/// Returned by [`Watcher::watch_info`](trait.Watcher.html#tymethod.watch_info) for every path
+/// passed to `watch`.
+///
+/// # Platform support
+///
+/// __Linux__
+///
+/// `watch_count` is the number of inotify watches of the path and the directories below it. Watches
+/// of directories inside several watched paths are counted for each of them.
+///
+/// __OS X, Windows__
+///
+/// A watched path uses a single handle, `watch_count` is always 1.
+///
+/// __Polling__
+///
+/// `watch_count` is the number of files and directories scanned for the path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WatchInfo {
+    /// The watched path
+    pub path: PathBuf,
+
+    /// Whether the sub-directories of the path are watched as well
+    pub recursive_mode: RecursiveMode,
+
+    /// Number of kernel watches used for the path, or of entries scanned for it when polling
+    pub watch_count: usize,
+
+    /// Whether changes of the path are reported
+    pub health: WatchHealth,
+}
+
+/// State of a watched path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WatchHealth {
+    /// Changes are reported
+    Healthy,
+
+    /// Some directories below the path could not be watched, e.g. because the maximum number of
+    /// watches was reached; changes inside them aren't reported
+    ///
+    /// Only reported on Linux.
+    Degraded,
+
+    /// The path has been removed or moved away, its changes are no longer reported
+    Lost,
+
+    /// The path doesn't exist (yet or anymore), it is watched once it has been created
+    ///
+    /// Only reported for watchers created with
+    /// [`Config::with_pending_watches`](struct.Config.html#method.with_pending_watches) or
+    /// [`Config::with_persistent_roots`](struct.Config.html#method.with_persistent_roots).
+    Pending,
+}
@@ -24,8 +24,15 @@ use std::sync::{Arc, Mutex};
 use std::sync::mpsc::{channel, Sender, Receiver};
 use std::thread;
 use std::time::Duration;
-use super::{RawEvent, DebouncedEvent, Error, op, Op, Result, Watcher, RecursiveMode};
-use super::debounce::{Debounce, EventTx};
+use super::{Config, RawEvent, DebouncedEvent, EventHandler, EventKind, Error, Filter, op, Op,
+            Result, Watcher, RecursiveMode, WatchId, WatchInfo, WatchHealth};
+use super::event::{RemoveKind, RenameMode};
+use super::watch_id::{self, WatchRegistry};
+use super::config;
+use super::debounce::EventTx;
+
+/// Options supported by the `ReadDirectoryChangesWatcher`
+const SUPPORTED_OPTIONS: &'static [&'static str] = &["relative_paths", "thread_name"];
 
 const BUF_SIZE: u32 = 16384;
 
@@ -47,8 +54,13 @@ struct ReadDirectoryRequest {
 }
 
 enum Action {
-    Watch(PathBuf, RecursiveMode),
+    Watch(PathBuf, u64, RecursiveMode, Filter),
     Unwatch(PathBuf),
+    RemoveHandle(PathBuf, u64),
+    Info(Sender<Vec<WatchInfo>>),
+    Pause(Sender<()>),
+    Resume(Sender<()>),
+    Flush(Sender<()>),
     Stop,
 }
 
@@ -60,6 +72,7 @@ pub enum MetaEvent {
 struct WatchState {
     dir_handle: HANDLE,
     complete_sem: HANDLE,
+    is_recursive: bool,
 }
 
 struct ReadDirectoryChangesServer {
@@ -75,13 +88,18 @@ impl ReadDirectoryChangesServer {
     fn start(event_tx: EventTx,
              meta_tx: Sender<MetaEvent>,
              cmd_tx: Sender<Result<PathBuf>>,
-             wakeup_sem: HANDLE)
-             -> Sender<Action> {
+             wakeup_sem: HANDLE,
+             thread_name: Option<&str>)
+             -> Result<Sender<Action>> {
 
         let (action_tx, action_rx) = channel();
         // it is, in fact, ok to send the semaphore across threads
         let sem_temp = wakeup_sem as u64;
-        thread::spawn(move || {
+        let mut builder = thread::Builder::new();
+        if let Some(name) = thread_name {
+            builder = builder.name(name.to_owned());
+        }
+        try!(builder.spawn(move || {
             let wakeup_sem = sem_temp as HANDLE;
             let server = ReadDirectoryChangesServer {
                 rx: action_rx,
@@ -92,8 +110,8 @@ impl ReadDirectoryChangesServer {
                 wakeup_sem: wakeup_sem,
             };
             server.run();
-        });
-        action_tx
+        }).map_err(Error::Io));
+        Ok(action_tx)
     }
 
     fn run(mut self) {
@@ -103,11 +121,37 @@ impl ReadDirectoryChangesServer {
 
             while let Ok(action) = self.rx.try_recv() {
                 match action {
-                    Action::Watch(path, recursive_mode) => {
-                        let res = self.add_watch(path, recursive_mode.is_recursive());
+                    Action::Watch(path, id, recursive_mode, filter) => {
+                        let res = self.add_handle(path, id, recursive_mode.is_recursive(), filter);
                         let _ = self.cmd_tx.send(res);
                     }
                     Action::Unwatch(path) => self.remove_watch(path),
+                    Action::RemoveHandle(path, id) => self.remove_handle(path, id),
+                    Action::Info(tx) => {
+                        let _ = tx.send(self.watch_info());
+                    }
+                    Action::Pause(tx) => {
+                        if let Ok(mut event_tx) = self.event_tx.lock() {
+                            event_tx.pause();
+                        }
+                        let _ = tx.send(());
+                    }
+                    Action::Resume(tx) => {
+                        if let Ok(mut event_tx) = self.event_tx.lock() {
+                            event_tx.resume();
+                        }
+                        let _ = tx.send(());
+                    }
+                    Action::Flush(tx) => {
+                        // run the completion routines of the reads that have completed
+                        unsafe {
+                            kernel32::SleepEx(0, TRUE);
+                        }
+                        if let Ok(mut event_tx) = self.event_tx.lock() {
+                            event_tx.flush();
+                        }
+                        let _ = tx.send(());
+                    }
                     Action::Stop => {
                         stopped = true;
                         for (_, ws) in &self.watches {
@@ -137,8 +181,63 @@ impl ReadDirectoryChangesServer {
         }
     }
 
+    /// Add a handle of a path, watching it if it isn't watched yet
+    fn add_handle(&mut self,
+                  path: PathBuf,
+                  id: u64,
+                  is_recursive: bool,
+                  filter: Filter)
+                  -> Result<PathBuf> {
+        if let Ok(mut event_tx) = self.event_tx.lock() {
+            event_tx.filters_mut().insert(path.clone(), id, filter, is_recursive);
+        }
+        if self.watches.contains_key(&path) {
+            self.update_watch(path.clone());
+            return Ok(path);
+        }
+
+        let res = self.add_watch(path.clone(), is_recursive);
+        if res.is_err() {
+            if let Ok(mut event_tx) = self.event_tx.lock() {
+                event_tx.filters_mut().remove_handle(&path, id);
+            }
+        }
+        res
+    }
+
+    /// Remove a handle of a watched path, the watch is removed along with its last handle
+    fn remove_handle(&mut self, path: PathBuf, id: u64) {
+        let remaining = match self.event_tx.lock() {
+            Ok(mut event_tx) => event_tx.filters_mut().remove_handle(&path, id),
+            Err(_) => return,
+        };
+        if remaining {
+            self.update_watch(path);
+        } else {
+            self.remove_watch(path);
+        }
+    }
+
+    /// Restart the watch of a path if the recursive mode of its handles has changed
+    fn update_watch(&mut self, path: PathBuf) {
+        let is_recursive = match self.event_tx.lock() {
+            Ok(event_tx) => event_tx.filters().is_recursive(&path),
+            Err(_) => return,
+        };
+        if self.watches.get(&path).map_or(true, |ws| ws.is_recursive == is_recursive) {
+            return;
+        }
+        if let Some(ws) = self.watches.remove(&path) {
+            stop_watch(&ws, &self.meta_tx);
+        }
+        let _ = self.add_watch(path, is_recursive);
+    }
+
     fn add_watch(&mut self, path: PathBuf, is_recursive: bool) -> Result<PathBuf> {
         // path must exist and be either a file or directory
+        if !path.exists() {
+            return Err(Error::PathNotFound(path));
+        }
         if !path.is_dir() && !path.is_file() {
             return Err(Error::Generic("Input watch path is neither a file nor a directory."
                 .to_owned()));
@@ -173,7 +272,7 @@ impl ReadDirectoryChangesServer {
                         .to_owned()))
                 } else {
                     // TODO: Call GetLastError for better error info?
-                    Err(Error::PathNotFound)
+                    Err(Error::PathNotFound(path.clone()))
                 };
                 return err;
             }
@@ -201,15 +300,37 @@ impl ReadDirectoryChangesServer {
         let ws = WatchState {
             dir_handle: handle,
             complete_sem: semaphore,
+            is_recursive: is_recursive,
         };
         self.watches.insert(path.clone(), ws);
         start_read(&rd, self.event_tx.clone(), handle);
         Ok(path.to_path_buf())
     }
 
+    fn watch_info(&self) -> Vec<WatchInfo> {
+        self.watches
+            .iter()
+            .map(|(path, ws)| {
+                WatchInfo {
+                    path: path.clone(),
+                    recursive_mode: RecursiveMode::from_recursive(ws.is_recursive),
+                    watch_count: 1,
+                    health: if path.exists() {
+                        WatchHealth::Healthy
+                    } else {
+                        WatchHealth::Lost
+                    },
+                }
+            })
+            .collect()
+    }
+
     fn remove_watch(&mut self, path: PathBuf) {
         if let Some(ws) = self.watches.remove(&path) {
             stop_watch(&ws, &self.meta_tx);
+            if let Ok(mut event_tx) = self.event_tx.lock() {
+                event_tx.filters_mut().remove(&path);
+            }
         }
     }
 }
@@ -281,12 +402,14 @@ fn start_read(rd: &ReadData, event_tx: Arc<Mutex<EventTx>>, handle: HANDLE) {
     }
 }
 
-fn send_pending_rename_event(event: Option<RawEvent>, event_tx: &mut EventTx) {
+fn send_pending_rename_event(event: Option<RawEvent>, batch: &mut Vec<RawEvent>) {
     if let Some(e) = event {
-        event_tx.send(RawEvent {
+        batch.push(RawEvent {
             path: e.path,
             op: Ok(op::REMOVE),
+            kind: EventKind::Remove(RemoveKind::Any),
             cookie: None,
+            relative: None,
         });
     }
 }
@@ -309,6 +432,8 @@ unsafe extern "system" fn handle_event(error_code: u32,
 
     let event_tx_lock = request.event_tx.lock();
     if let Ok(mut event_tx) = event_tx_lock {
+        // the events of one read are delivered as one batch
+        let mut batch = Vec::new();
         let mut rename_event = None;
 
         // The FILE_NOTIFY_INFORMATION struct has a variable length due to the variable length
@@ -332,12 +457,14 @@ unsafe extern "system" fn handle_event(error_code: u32,
 
             if !skip {
                 if (*cur_entry).Action == winnt::FILE_ACTION_RENAMED_OLD_NAME {
-                    send_pending_rename_event(rename_event, &mut event_tx);
+                    send_pending_rename_event(rename_event, &mut batch);
                     if request.data.file.is_some() {
-                        event_tx.send(RawEvent {
+                        batch.push(RawEvent {
                             path: Some(path),
                             op: Ok(op::RENAME),
+                            kind: EventKind::Rename(RenameMode::From),
                             cookie: None,
+                            relative: None,
                         });
                         rename_event = None;
                     } else {
@@ -345,7 +472,9 @@ unsafe extern "system" fn handle_event(error_code: u32,
                         rename_event = Some(RawEvent {
                             path: Some(path),
                             op: Ok(op::RENAME),
+                            kind: EventKind::Rename(RenameMode::From),
                             cookie: Some(COOKIE_COUNTER),
+                            relative: None,
                         });
                     }
                 } else {
@@ -356,7 +485,7 @@ unsafe extern "system" fn handle_event(error_code: u32,
                         winnt::FILE_ACTION_RENAMED_NEW_NAME => {
                             if let Some(e) = rename_event {
                                 if let Some(cookie) = e.cookie {
-                                    event_tx.send(e);
+                                    batch.push(e);
                                     o.insert(op::RENAME);
                                     c = Some(cookie);
                                 } else {
@@ -373,13 +502,22 @@ unsafe extern "system" fn handle_event(error_code: u32,
                         _ => (),
                     };
 
-                    send_pending_rename_event(rename_event, &mut event_tx);
+                    send_pending_rename_event(rename_event, &mut batch);
                     rename_event = None;
 
-                    event_tx.send(RawEvent {
+                    // Windows doesn't tell files from directories or data from meta data changes
+                    let kind = if c.is_some() {
+                        EventKind::Rename(RenameMode::To)
+                    } else {
+                        EventKind::from_op(o)
+                    };
+
+                    batch.push(RawEvent {
                         path: Some(path),
                         op: Ok(o),
+                        kind: kind,
                         cookie: c,
+                        relative: None,
                     });
                 }
             }
@@ -391,7 +529,8 @@ unsafe extern "system" fn handle_event(error_code: u32,
             cur_entry = mem::transmute(cur_offset);
         }
 
-        send_pending_rename_event(rename_event, &mut event_tx);
+        send_pending_rename_event(rename_event, &mut batch);
+        event_tx.send_all(batch);
     }
 }
 
@@ -399,35 +538,30 @@ pub struct ReadDirectoryChangesWatcher {
     tx: Sender<Action>,
     cmd_rx: Receiver<Result<PathBuf>>,
     wakeup_sem: HANDLE,
+    registry: WatchRegistry,
 }
 
 impl ReadDirectoryChangesWatcher {
-    pub fn create(tx: Sender<RawEvent>,
-                  meta_tx: Sender<MetaEvent>)
-                  -> Result<ReadDirectoryChangesWatcher> {
-        let (cmd_tx, cmd_rx) = channel();
-
-        let wakeup_sem =
-            unsafe { kernel32::CreateSemaphoreW(ptr::null_mut(), 0, 1, ptr::null_mut()) };
-        if wakeup_sem == ptr::null_mut() || wakeup_sem == INVALID_HANDLE_VALUE {
-            return Err(Error::Generic("Failed to create wakeup semaphore.".to_owned()));
-        }
-
-        let event_tx = EventTx::Raw { tx: tx };
-
-        let action_tx = ReadDirectoryChangesServer::start(event_tx, meta_tx, cmd_tx, wakeup_sem);
+    pub fn create<H: EventHandler<RawEvent>>(handler: H,
+                                             meta_tx: Sender<MetaEvent>)
+                                             -> Result<ReadDirectoryChangesWatcher> {
+        let config = Config::new();
+        ReadDirectoryChangesWatcher::start(EventTx::new_raw(handler, &config), meta_tx, &config)
+    }
 
-        Ok(ReadDirectoryChangesWatcher {
-            tx: action_tx,
-            cmd_rx: cmd_rx,
-            wakeup_sem: wakeup_sem,
-        })
+    pub fn create_debounced<H: EventHandler<DebouncedEvent>>(handler: H,
+                                                             meta_tx: Sender<MetaEvent>,
+                                                             delay: Duration)
+                                                             -> Result<ReadDirectoryChangesWatcher> {
+        let config = Config::new().with_debounce_delay(delay);
+        let event_tx = try!(EventTx::new_debounced(handler, &config));
+        ReadDirectoryChangesWatcher::start(event_tx, meta_tx, &config)
     }
 
-    pub fn create_debounced(tx: Sender<DebouncedEvent>,
-                            meta_tx: Sender<MetaEvent>,
-                            delay: Duration)
-                            -> Result<ReadDirectoryChangesWatcher> {
+    fn start(event_tx: EventTx,
+             meta_tx: Sender<MetaEvent>,
+             config: &Config)
+             -> Result<ReadDirectoryChangesWatcher> {
         let (cmd_tx, cmd_rx) = channel();
 
         let wakeup_sem =
@@ -436,21 +570,33 @@ impl ReadDirectoryChangesWatcher {
             return Err(Error::Generic("Failed to create wakeup semaphore.".to_owned()));
         }
 
-        let event_tx = EventTx::Debounced {
-            tx: tx.clone(),
-            debounce: Debounce::new(delay, tx),
-        };
+        let action_tx = try!(ReadDirectoryChangesServer::start(event_tx,
+                                                               meta_tx,
+                                                               cmd_tx,
+                                                               wakeup_sem,
+                                                               config.thread_name()));
 
-        let action_tx = ReadDirectoryChangesServer::start(event_tx, meta_tx, cmd_tx, wakeup_sem);
+        // unwatch the path once its last handle is dropped
+        let unwatch_tx = Mutex::new(action_tx.clone());
+        let sem_temp = wakeup_sem as u64;
+        let registry = WatchRegistry::new(move |path, id| {
+            if let Ok(tx) = unwatch_tx.lock() {
+                let _ = tx.send(Action::RemoveHandle(path.to_owned(), id));
+            }
+            unsafe {
+                kernel32::ReleaseSemaphore(sem_temp as HANDLE, 1, ptr::null_mut());
+            }
+        });
 
         Ok(ReadDirectoryChangesWatcher {
             tx: action_tx,
             cmd_rx: cmd_rx,
             wakeup_sem: wakeup_sem,
+            registry: registry,
         })
     }
 
-    fn wakeup_server(&mut self) {
+    fn wakeup_server(&self) {
         // breaks the server out of its wait state.  right now this is really just an optimization,
         // so that if you add a watch you don't block for 100ms in watch() while the
         // server sleeps.
@@ -459,6 +605,15 @@ impl ReadDirectoryChangesWatcher {
         }
     }
 
+    /// Send an action and wait until the server has handled it
+    fn send_action_wait(&self, action: Action, rx: Receiver<()>) -> Result<()> {
+        if self.tx.send(action).is_err() {
+            return Err(Error::Generic("Error sending to internal channel".to_owned()));
+        }
+        self.wakeup_server();
+        rx.recv().map_err(|_| Error::Generic("Error receiving from internal channel".to_owned()))
+    }
+
     fn send_action_require_ack(&mut self, action: Action, pb: &PathBuf) -> Result<()> {
         match self.tx.send(action) {
             Err(_) => Err(Error::Generic("Error sending to internal channel".to_owned())),
@@ -492,19 +647,30 @@ impl ReadDirectoryChangesWatcher {
 }
 
 impl Watcher for ReadDirectoryChangesWatcher {
-    fn new_raw(tx: Sender<RawEvent>) -> Result<ReadDirectoryChangesWatcher> {
+    fn new_raw_with_config<H: EventHandler<RawEvent>>(handler: H,
+                                                      config: Config)
+                                                      -> Result<ReadDirectoryChangesWatcher> {
+        try!(config::check_supported(&config, SUPPORTED_OPTIONS, false));
         // create dummy channel for meta event
         let (meta_tx, _) = channel();
-        ReadDirectoryChangesWatcher::create(tx, meta_tx)
+        ReadDirectoryChangesWatcher::start(EventTx::new_raw(handler, &config), meta_tx, &config)
     }
 
-    fn new(tx: Sender<DebouncedEvent>, delay: Duration) -> Result<ReadDirectoryChangesWatcher> {
+    fn new_with_config<H: EventHandler<DebouncedEvent>>(handler: H,
+                                                        config: Config)
+                                                        -> Result<ReadDirectoryChangesWatcher> {
+        try!(config::check_supported(&config, SUPPORTED_OPTIONS, true));
+        let event_tx = try!(EventTx::new_debounced(handler, &config));
         // create dummy channel for meta event
         let (meta_tx, _) = channel();
-        ReadDirectoryChangesWatcher::create_debounced(tx, meta_tx, delay)
+        ReadDirectoryChangesWatcher::start(event_tx, meta_tx, &config)
     }
 
-    fn watch<P: AsRef<Path>>(&mut self, path: P, recursive_mode: RecursiveMode) -> Result<()> {
+    fn watch_filtered<P: AsRef<Path>>(&mut self,
+                                      path: P,
+                                      recursive_mode: RecursiveMode,
+                                      filter: Filter)
+                                      -> Result<WatchId> {
         let pb = if path.as_ref().is_absolute() {
             path.as_ref().to_owned()
         } else {
@@ -516,7 +682,15 @@ impl Watcher for ReadDirectoryChangesWatcher {
             return Err(Error::Generic("Input watch path is neither a file nor a directory."
                 .to_owned()));
         }
-        self.send_action_require_ack(Action::Watch(pb.clone(), recursive_mode), &pb)
+        let id = self.registry.register(pb.clone());
+        let action = Action::Watch(pb.clone(), watch_id::id(&id), recursive_mode, filter);
+        match self.send_action_require_ack(action, &pb) {
+            Ok(()) => Ok(id),
+            Err(e) => {
+                self.registry.discard(id);
+                Err(e)
+            }
+        }
     }
 
     fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
@@ -527,11 +701,38 @@ impl Watcher for ReadDirectoryChangesWatcher {
             p.join(path)
         };
         let res = self.tx
-            .send(Action::Unwatch(pb))
+            .send(Action::Unwatch(pb.clone()))
             .map_err(|_| Error::Generic("Error sending to internal channel".to_owned()));
         self.wakeup_server();
+        if res.is_ok() {
+            self.registry.forget(&pb);
+        }
         res
     }
+
+    fn watch_info(&self) -> Result<Vec<WatchInfo>> {
+        let (tx, rx) = channel();
+        if self.tx.send(Action::Info(tx)).is_err() {
+            return Err(Error::Generic("Error sending to internal channel".to_owned()));
+        }
+        self.wakeup_server();
+        rx.recv().map_err(|_| Error::Generic("Error receiving from internal channel".to_owned()))
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        let (tx, rx) = channel();
+        self.send_action_wait(Action::Pause(tx), rx)
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        let (tx, rx) = channel();
+        self.send_action_wait(Action::Resume(tx), rx)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        let (tx, rx) = channel();
+        self.send_action_wait(Action::Flush(tx), rx)
+    }
 }
 
 impl Drop for ReadDirectoryChangesWatcher {
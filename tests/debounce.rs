@@ -41,7 +41,26 @@ fn create_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create("file1");
+
+    assert_eq!(recv_events_debounced(&rx), vec![
+        DebouncedEvent::Create(tdir.mkpath("file1")),
+    ]);
+}
+
+#[test]
+fn create_file_closure() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    sleep_macos(10);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new(move |event: DebouncedEvent| {
+        let _ = tx.send(event);
+    }, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.create("file1");
 
@@ -62,7 +81,7 @@ fn write_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.write("file1");
 
@@ -72,6 +91,28 @@ fn write_file() {
     ]);
 }
 
+#[test]
+fn write_file_without_notice() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec![
+        "file1",
+    ]);
+
+    sleep_macos(35_000);
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::new().with_debounce_delay(Duration::from_secs(DELAY_S)).with_notice_write(false);
+    let mut watcher: RecommendedWatcher = Watcher::new_with_config(tx, config).expect("failed to create debounced watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.write("file1");
+
+    assert_eq!(recv_events_debounced(&rx), vec![
+        DebouncedEvent::Write(tdir.mkpath("file1")),
+    ]);
+}
+
 #[test]
 fn write_long_file() {
     let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
@@ -84,7 +125,7 @@ fn write_long_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     let wait = Duration::from_millis(DELAY_S * 500);
     tdir.write("file1");
@@ -128,7 +169,7 @@ fn modify_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.chmod("file1");
 
@@ -157,7 +198,7 @@ fn delete_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.remove("file1");
 
@@ -167,6 +208,28 @@ fn delete_file() {
     ]);
 }
 
+#[test]
+fn delete_file_without_notice() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec![
+        "file1",
+    ]);
+
+    sleep_macos(35_000);
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::new().with_debounce_delay(Duration::from_secs(DELAY_S)).with_notice_remove(false);
+    let mut watcher: RecommendedWatcher = Watcher::new_with_config(tx, config).expect("failed to create debounced watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.remove("file1");
+
+    assert_eq!(recv_events_debounced(&rx), vec![
+        DebouncedEvent::Remove(tdir.mkpath("file1")),
+    ]);
+}
+
 #[test]
 fn rename_file() {
     let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
@@ -179,7 +242,7 @@ fn rename_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.rename("file1", "file2");
 
@@ -197,7 +260,7 @@ fn create_write_modify_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.create("file1");
     tdir.write("file1");
@@ -216,7 +279,7 @@ fn create_delete_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.create("file1");
     sleep_macos(10);
@@ -237,7 +300,7 @@ fn delete_create_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.remove("file1");
     sleep_macos(10);
@@ -257,7 +320,7 @@ fn create_rename_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.create("file1");
     tdir.rename("file1", "file2");
@@ -275,7 +338,7 @@ fn create_rename_delete_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.create("file1");
     sleep_macos(10);
@@ -310,7 +373,7 @@ fn create_rename_overwrite_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.create("file1");
     tdir.rename("file1", "file2");
@@ -345,7 +408,7 @@ fn write_rename_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.write("file1");
     tdir.rename("file1", "file2");
@@ -370,7 +433,7 @@ fn rename_write_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.rename("file1", "file2");
     sleep_macos(10);
@@ -396,7 +459,7 @@ fn modify_rename_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.chmod("file1");
     tdir.rename("file1", "file2");
@@ -430,7 +493,7 @@ fn rename_modify_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.rename("file1", "file2");
     sleep_macos(10);
@@ -465,7 +528,7 @@ fn rename_rename_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.rename("file1", "file2");
     sleep_macos(10);
@@ -489,7 +552,7 @@ fn write_delete_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.write("file1");
     tdir.remove("file1");
@@ -509,7 +572,7 @@ fn create_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.create("dir1");
 
@@ -530,7 +593,7 @@ fn modify_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.chmod("dir1");
 
@@ -559,7 +622,7 @@ fn delete_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.remove("dir1");
 
@@ -581,7 +644,7 @@ fn rename_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.rename("dir1", "dir2");
 
@@ -599,7 +662,7 @@ fn create_modify_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.create("dir1");
     tdir.chmod("dir1");
@@ -617,7 +680,7 @@ fn create_delete_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.create("dir1");
     sleep_macos(10);
@@ -638,7 +701,7 @@ fn delete_create_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.remove("dir1");
     sleep_macos(10);
@@ -658,7 +721,7 @@ fn create_rename_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.create("dir1");
     tdir.rename("dir1", "dir2");
@@ -676,7 +739,7 @@ fn create_rename_delete_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.create("dir1");
     sleep_macos(10);
@@ -705,7 +768,7 @@ fn create_rename_overwrite_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.create("dir1");
     tdir.rename("dir1", "dir2");
@@ -734,7 +797,7 @@ fn modify_rename_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.chmod("dir1");
     tdir.chmod("dir1"); // needed by os x
@@ -769,7 +832,7 @@ fn rename_modify_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.rename("dir1", "dir2");
     sleep_macos(10);
@@ -817,7 +880,7 @@ fn rename_rename_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.rename("dir1", "dir2");
     sleep_macos(10);
@@ -841,7 +904,7 @@ fn modify_delete_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.chmod("dir1");
     tdir.chmod("dir1"); // needed by windows
@@ -861,3 +924,233 @@ fn modify_delete_directory() {
         ]);
     }
 }
+
+#[test]
+fn poll_create_file() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: PollWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    sleep(1100); // PollWatcher has only a resolution of 1 second
+
+    tdir.create("file1");
+
+    sleep(DELAY_S * 1000); // wait for the next scan
+
+    assert_eq!(recv_events_debounced(&rx), vec![
+        DebouncedEvent::Create(tdir.mkpath("file1")),
+    ]);
+}
+
+#[test]
+fn poll_write_file() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec![
+        "file1",
+    ]);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: PollWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch file");
+
+    sleep(1100); // PollWatcher has only a resolution of 1 second
+
+    tdir.write("file1");
+
+    sleep(DELAY_S * 1000); // wait for the next scan
+
+    assert_eq!(recv_events_debounced(&rx), vec![
+        DebouncedEvent::NoticeWrite(tdir.mkpath("file1")),
+        DebouncedEvent::Write(tdir.mkpath("file1")),
+    ]);
+}
+
+#[test]
+fn poll_delete_file() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec![
+        "file1",
+    ]);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: PollWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    sleep(1100); // PollWatcher has only a resolution of 1 second
+
+    tdir.remove("file1");
+
+    sleep(DELAY_S * 1000); // wait for the next scan
+
+    assert_eq!(recv_events_debounced(&rx), vec![
+        DebouncedEvent::NoticeRemove(tdir.mkpath("file1")),
+        DebouncedEvent::Remove(tdir.mkpath("file1")),
+    ]);
+}
+
+#[test]
+fn pause_resume() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    sleep_macos(10);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    watcher.pause().expect("failed to pause watcher");
+
+    tdir.create("file1");
+
+    sleep(100);
+
+    watcher.resume().expect("failed to resume watcher");
+
+    assert_eq!(recv_events_debounced(&rx), vec![
+        DebouncedEvent::Rescan,
+    ]);
+}
+
+#[test]
+fn pause_resume_pending_event() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    sleep_macos(10);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create("file1");
+
+    sleep(100);
+
+    // the event is being debounced, it's due while the watcher is paused
+    watcher.pause().expect("failed to pause watcher");
+
+    assert_eq!(recv_events_debounced(&rx), vec![]);
+
+    watcher.resume().expect("failed to resume watcher");
+
+    assert_eq!(recv_events_debounced(&rx), vec![
+        DebouncedEvent::Rescan,
+    ]);
+}
+
+#[test]
+fn flush() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    sleep_macos(10);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create("file1");
+
+    // delivered without waiting for the debounce delay
+    watcher.flush().expect("failed to flush watcher");
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![
+        DebouncedEvent::Create(tdir.mkpath("file1")),
+    ]);
+}
+
+#[test]
+fn batched() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    sleep_macos(10);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new(Batched::new(tx), Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create("file1");
+    tdir.create("file2");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let mut batches: Vec<Vec<DebouncedEvent>> = rx.try_iter().collect();
+    assert_eq!(batches.len(), 1);
+    batches[0].sort_by_key(|event| format!("{:?}", event));
+    assert_eq!(batches[0], vec![
+        DebouncedEvent::Create(tdir.mkpath("file1")),
+        DebouncedEvent::Create(tdir.mkpath("file2")),
+    ]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn create_directory_contents() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    // the contents are created before the directories can be watched
+    tdir.create_all(vec!["dir1/dir11/dir111/file1"]);
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let mut actual: Vec<DebouncedEvent> = rx.try_iter().collect();
+    actual.sort_by_key(|event| format!("{:?}", event));
+    assert_eq!(actual, vec![
+        DebouncedEvent::Create(tdir.mkpath("dir1")),
+        DebouncedEvent::Create(tdir.mkpath("dir1/dir11")),
+        DebouncedEvent::Create(tdir.mkpath("dir1/dir11/dir111")),
+        DebouncedEvent::Create(tdir.mkpath("dir1/dir11/dir111/file1")),
+    ]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn access_events() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["file1"]);
+    tdir.write("file1");
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::new().with_debounce_delay(Duration::from_secs(DELAY_S)).with_access_events(true);
+    let mut watcher: RecommendedWatcher = Watcher::new_with_config(tx, config).expect("failed to create debounced watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.read("file1");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    // access events are delivered without waiting for the delay
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![
+        DebouncedEvent::Open(tdir.mkpath("file1")),
+        DebouncedEvent::Access(tdir.mkpath("file1")),
+        DebouncedEvent::CloseNowrite(tdir.mkpath("file1")),
+    ]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn watch_removed() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["file1"]);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(DELAY_S)).expect("failed to create debounced watcher");
+    let _watch = watcher.watch(tdir.mkpath("file1"), RecursiveMode::NonRecursive).expect("failed to watch file");
+
+    tdir.remove("file1");
+
+    // the removed watch is reported right away, the removal after the delay
+    assert_eq!(recv_events_debounced(&rx), vec![
+        DebouncedEvent::NoticeRemove(tdir.mkpath("file1")),
+        DebouncedEvent::WatchRemoved(tdir.mkpath("file1")),
+        DebouncedEvent::Remove(tdir.mkpath("file1")),
+    ]);
+}
@@ -0,0 +1,42 @@
+extern crate notify;
+
+use notify::{op, Op, EventKind};
+use notify::event::{AccessKind, AccessMode, CreateKind, MetadataKind, ModifyKind, RemoveKind,
+                    RenameMode};
+
+#[test]
+fn kind_to_op() {
+    assert_eq!(Op::from(EventKind::Create(CreateKind::Dir)), op::CREATE);
+    assert_eq!(Op::from(EventKind::Remove(RemoveKind::File)), op::REMOVE);
+    assert_eq!(Op::from(EventKind::Rename(RenameMode::Both)), op::RENAME);
+    assert_eq!(Op::from(EventKind::Modify(ModifyKind::Data)), op::WRITE);
+    assert_eq!(Op::from(EventKind::Modify(ModifyKind::Any)), op::WRITE);
+    assert_eq!(Op::from(EventKind::Modify(ModifyKind::Metadata(MetadataKind::Ownership))),
+               op::CHMOD);
+    assert_eq!(Op::from(EventKind::Access(AccessKind::Close(AccessMode::Write))),
+               op::CLOSE_WRITE);
+    assert_eq!(Op::from(EventKind::Access(AccessKind::Read)), op::ACCESS);
+    assert_eq!(Op::from(EventKind::Access(AccessKind::Open)), op::OPEN);
+    assert_eq!(Op::from(EventKind::Access(AccessKind::Close(AccessMode::Read))),
+               op::CLOSE_NOWRITE);
+    assert_eq!(Op::from(EventKind::Rescan), op::RESCAN);
+    assert_eq!(Op::from(EventKind::WatchRemoved), op::WATCH_REMOVED);
+    assert_eq!(Op::from(EventKind::Unmounted), op::UNMOUNTED);
+}
+
+#[test]
+fn unknown_kind_to_op() {
+    assert_eq!(Op::from(EventKind::Any), Op::empty());
+    assert_eq!(Op::from(EventKind::Access(AccessKind::Any)), Op::empty());
+    assert_eq!(Op::from(EventKind::Access(AccessKind::Close(AccessMode::Any))), Op::empty());
+}
+
+#[test]
+fn op_to_kind() {
+    for &o in &[op::CHMOD, op::CREATE, op::REMOVE, op::RENAME, op::WRITE, op::CLOSE_WRITE,
+                op::RESCAN, op::ACCESS, op::OPEN, op::CLOSE_NOWRITE, op::WATCH_REMOVED,
+                op::UNMOUNTED] {
+        assert_eq!(Op::from(EventKind::from_op(o)), o);
+    }
+    assert_eq!(EventKind::from_op(op::CREATE | op::WRITE), EventKind::Any);
+}
@@ -53,7 +53,7 @@ fn watch_relative() {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch("dir1", RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch("dir1", RecursiveMode::Recursive).expect("failed to watch directory");
 
         sleep_windows(100);
 
@@ -77,7 +77,7 @@ fn watch_relative() {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch("file1", RecursiveMode::Recursive).expect("failed to watch file");
+        let _watch = watcher.watch("file1", RecursiveMode::Recursive).expect("failed to watch file");
 
         sleep_windows(100);
 
@@ -102,7 +102,7 @@ fn watch_relative() {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch("dir1", RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch("dir1", RecursiveMode::Recursive).expect("failed to watch directory");
 
         sleep_windows(100);
 
@@ -121,7 +121,7 @@ fn watch_relative() {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch("file1", RecursiveMode::Recursive).expect("failed to watch file");
+        let _watch = watcher.watch("file1", RecursiveMode::Recursive).expect("failed to watch file");
 
         sleep_windows(100);
 
@@ -143,7 +143,7 @@ fn watch_absolute_directory() {
     let watch_path = tdir.path().join("dir1");
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -168,7 +168,7 @@ fn watch_absolute_file() {
     let watch_path = tdir.path().join("file1");
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -198,7 +198,7 @@ fn watch_absolute_network_directory() {
     let watch_path = tdir.path().join("dir1");
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -222,7 +222,7 @@ fn watch_absolute_network_file() {
     let watch_path = tdir.path().join("file1");
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -243,7 +243,7 @@ fn watch_canonicalized_directory() {
     let watch_path = tdir.path().canonicalize().expect("failed to canonicalize path").join("dir1");
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -262,7 +262,7 @@ fn watch_canonicalized_file() {
     let watch_path = tdir.path().canonicalize().expect("failed to canonicalize path").join("file1");
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -286,7 +286,7 @@ fn watch_canonicalized_network_directory() {
     let watch_path = tdir.path().canonicalize().expect("failed to canonicalize path").join("dir1");
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -310,7 +310,7 @@ fn watch_canonicalized_network_file() {
     let watch_path = tdir.path().canonicalize().expect("failed to canonicalize path").join("file1");
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&watch_path, RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -0,0 +1,73 @@
+extern crate notify;
+
+use notify::Filter;
+use std::path::Path;
+
+#[test]
+fn empty_filter() {
+    let filter = Filter::new();
+    assert!(filter.is_empty());
+    assert!(filter.matches(Path::new("file1")));
+    assert!(filter.matches(Path::new("dir1/file1")));
+    assert!(!filter.is_excluded(Path::new("dir1")));
+}
+
+#[test]
+fn include_patterns() {
+    let mut filter = Filter::new();
+    filter.include("**/*.rs").expect("failed to add include pattern");
+    filter.include("Cargo.toml").expect("failed to add include pattern");
+
+    assert!(filter.matches(Path::new("lib.rs")));
+    assert!(filter.matches(Path::new("src/lib.rs")));
+    assert!(filter.matches(Path::new("Cargo.toml")));
+    assert!(!filter.matches(Path::new("src/Cargo.toml")));
+    assert!(!filter.matches(Path::new("README.md")));
+
+    // include patterns never prune directories
+    assert!(!filter.is_excluded(Path::new("src")));
+}
+
+#[test]
+fn exclude_patterns() {
+    let mut filter = Filter::new();
+    filter.exclude("target").expect("failed to add exclude pattern");
+    filter.exclude("**/.git").expect("failed to add exclude pattern");
+
+    assert!(filter.is_excluded(Path::new("target")));
+    assert!(filter.is_excluded(Path::new("target/debug/build")));
+    assert!(filter.is_excluded(Path::new(".git")));
+    assert!(filter.is_excluded(Path::new("vendor/crate/.git/HEAD")));
+    assert!(!filter.is_excluded(Path::new("src/target.rs")));
+    assert!(!filter.is_excluded(Path::new("src/target")));
+
+    assert!(!filter.matches(Path::new("target/debug")));
+    assert!(filter.matches(Path::new("src/lib.rs")));
+}
+
+#[test]
+fn exclude_overrides_include() {
+    let mut filter = Filter::new();
+    filter.include("**/*.rs").expect("failed to add include pattern");
+    filter.exclude("target").expect("failed to add exclude pattern");
+
+    assert!(filter.matches(Path::new("src/lib.rs")));
+    assert!(!filter.matches(Path::new("target/build.rs")));
+}
+
+#[test]
+fn watched_path_is_never_filtered() {
+    let mut filter = Filter::new();
+    filter.include("*.rs").expect("failed to add include pattern");
+    filter.exclude("*").expect("failed to add exclude pattern");
+
+    assert!(filter.matches(Path::new("")));
+}
+
+#[test]
+fn invalid_pattern() {
+    let mut filter = Filter::new();
+    assert!(filter.include("[").is_err());
+    assert!(filter.exclude("***").is_err());
+    assert!(filter.is_empty());
+}
@@ -24,7 +24,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.create("file1");
         tdir.remove("file1");
@@ -48,7 +48,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.create("file1");
 
@@ -76,7 +76,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.create("file1");
 
@@ -104,7 +104,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.create("file1");
 
@@ -132,7 +132,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.create("file1");
 
@@ -160,7 +160,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.create("file1");
 
@@ -188,7 +188,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.create("file1");
 
@@ -216,7 +216,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.create("file1");
 
@@ -248,7 +248,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.rename("file1a", "file1b");
         tdir.rename("file1b", "file1c");
@@ -275,7 +275,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.rename("file1a", "file1b");
         sleep(10);
@@ -304,7 +304,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.rename("file1a", "file1b");
         sleep(20);
@@ -333,7 +333,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.rename("file1a", "file1b");
         tdir.rename("file1b", "file1a");
@@ -359,7 +359,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.rename("file1a", "file1b");
         sleep(10);
@@ -388,7 +388,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.rename("file1a", "file1b");
         sleep(20);
@@ -417,7 +417,7 @@ mod timing_tests {
 
         let (tx, rx) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
         tdir.rename("file1a", "file1b");
         sleep(10);
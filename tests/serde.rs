@@ -0,0 +1,158 @@
+#![cfg(feature = "serde")]
+
+extern crate notify;
+extern crate serde_json;
+
+use notify::*;
+use notify::event::{CreateKind, RenameMode};
+use std::io;
+use std::path::PathBuf;
+
+#[test]
+fn serialize_op() {
+    assert_eq!(serde_json::to_string(&(op::CREATE | op::WRITE)).unwrap(),
+               r#"["CREATE","WRITE"]"#);
+    assert_eq!(serde_json::to_string(&Op::empty()).unwrap(), "[]");
+
+    let o: Op = serde_json::from_str(r#"["RENAME","CLOSE_WRITE"]"#).unwrap();
+    assert_eq!(o, op::RENAME | op::CLOSE_WRITE);
+
+    let o: Op = serde_json::from_str(r#"["ACCESS","OPEN","CLOSE_NOWRITE"]"#).unwrap();
+    assert_eq!(o, op::ACCESS | op::OPEN | op::CLOSE_NOWRITE);
+
+    assert_eq!(serde_json::to_string(&(op::WATCH_REMOVED | op::UNMOUNTED)).unwrap(),
+               r#"["WATCH_REMOVED","UNMOUNTED"]"#);
+
+    assert!(serde_json::from_str::<Op>(r#"["MOVE"]"#).is_err());
+}
+
+#[test]
+fn serialize_raw_event() {
+    let event = RawEvent {
+        path: Some(PathBuf::from("/tmp/file1")),
+        op: Ok(op::CREATE),
+        kind: EventKind::Create(CreateKind::File),
+        cookie: None,
+        relative: None,
+    };
+    let json = serde_json::to_string(&event).unwrap();
+    assert_eq!(json,
+               r#"{"path":"/tmp/file1","op":{"Ok":["CREATE"]},"kind":{"Create":"File"},"cookie":null}"#);
+
+    let event: RawEvent = serde_json::from_str(&json).unwrap();
+    assert_eq!(event.path, Some(PathBuf::from("/tmp/file1")));
+    assert_eq!(event.op.unwrap(), op::CREATE);
+    assert_eq!(event.kind, EventKind::Create(CreateKind::File));
+
+    let event = RawEvent {
+        path: Some(PathBuf::from("/tmp/file2")),
+        op: Ok(op::RENAME),
+        kind: EventKind::Rename(RenameMode::To),
+        cookie: Some(42),
+        relative: None,
+    };
+    let event: RawEvent = serde_json::from_str(&serde_json::to_string(&event).unwrap()).unwrap();
+    assert_eq!(event.kind, EventKind::Rename(RenameMode::To));
+    assert_eq!(event.cookie, Some(42));
+
+    let event = RawEvent {
+        path: Some(PathBuf::from("/tmp/dir/file3")),
+        op: Ok(op::WRITE),
+        kind: EventKind::Any,
+        cookie: None,
+        relative: Some((PathBuf::from("/tmp/dir"), PathBuf::from("file3"))),
+    };
+    let json = serde_json::to_string(&event).unwrap();
+    assert!(json.ends_with(r#""relative":["/tmp/dir","file3"]}"#));
+    let event: RawEvent = serde_json::from_str(&json).unwrap();
+    assert_eq!(event.relative,
+               Some((PathBuf::from("/tmp/dir"), PathBuf::from("file3"))));
+}
+
+#[test]
+fn serialize_debounced_event() {
+    let events = vec![DebouncedEvent::Create(PathBuf::from("/tmp/file1")),
+                      DebouncedEvent::Rename(PathBuf::from("/tmp/file1"),
+                                             PathBuf::from("/tmp/file2")),
+                      DebouncedEvent::Rescan];
+    let json = serde_json::to_string(&events).unwrap();
+    assert_eq!(json,
+               r#"[{"Create":"/tmp/file1"},{"Rename":["/tmp/file1","/tmp/file2"]},"Rescan"]"#);
+
+    let actual: Vec<DebouncedEvent> = serde_json::from_str(&json).unwrap();
+    assert_eq!(actual, events);
+}
+
+#[test]
+fn serialize_error() {
+    let json = serde_json::to_string(&Error::Generic("Some error".to_owned())).unwrap();
+    assert_eq!(json, r#"{"kind":"generic","message":"Some error","errno":null}"#);
+
+    let json = serde_json::to_string(&Error::UnsupportedOption("poll_interval")).unwrap();
+    assert_eq!(json,
+               r#"{"kind":"unsupported_option","message":"Unsupported option: poll_interval","errno":null,"option":"poll_interval"}"#);
+    match serde_json::from_str(&json).unwrap() {
+        Error::UnsupportedOption(option) => assert_eq!(option, "poll_interval"),
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    match serde_json::from_str(r#"{"kind":"path_not_found","message":"","errno":null,"path":"/tmp/dir1"}"#).unwrap() {
+        Error::PathNotFound(path) => assert_eq!(path, PathBuf::from("/tmp/dir1")),
+        e => panic!("unexpected error: {:?}", e),
+    }
+    assert!(serde_json::from_str::<Error>(r#"{"kind":"path_not_found","message":"","errno":null}"#)
+        .is_err());
+
+    assert!(serde_json::from_str::<Error>(r#"{"kind":"other","message":"","errno":null}"#)
+        .is_err());
+}
+
+#[test]
+fn serialize_path_error() {
+    let json = serde_json::to_string(&Error::MaxFilesWatch(PathBuf::from("/tmp/dir1"))).unwrap();
+    assert_eq!(json,
+               r#"{"kind":"max_files_watch","message":"Maximum number of watches reached while watching /tmp/dir1","errno":null,"path":"/tmp/dir1"}"#);
+    match serde_json::from_str(&json).unwrap() {
+        Error::MaxFilesWatch(path) => assert_eq!(path, PathBuf::from("/tmp/dir1")),
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    assert!(serde_json::from_str::<Error>(r#"{"kind":"permission_denied","message":"","errno":null}"#)
+        .is_err());
+}
+
+#[test]
+fn serialize_io_error() {
+    let error = Error::Io(io::Error::from_raw_os_error(2));
+    let json = serde_json::to_value(&error).unwrap();
+    assert_eq!(json["kind"], "io");
+    assert_eq!(json["errno"], 2);
+    assert_eq!(json["message"], io::Error::from_raw_os_error(2).to_string());
+
+    match serde_json::from_value(json).unwrap() {
+        Error::Io(err) => assert_eq!(err.raw_os_error(), Some(2)),
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    let error = Error::Io(io::Error::new(io::ErrorKind::Other, "Some error"));
+    match serde_json::from_str(&serde_json::to_string(&error).unwrap()).unwrap() {
+        Error::Io(err) => assert_eq!(err.to_string(), "Some error"),
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn serialize_watch_info() {
+    let info = WatchInfo {
+        path: PathBuf::from("/tmp/dir1"),
+        recursive_mode: RecursiveMode::Recursive,
+        watch_count: 12,
+        health: WatchHealth::Degraded,
+    };
+    let json = serde_json::to_string(&info).unwrap();
+    assert_eq!(json,
+               r#"{"path":"/tmp/dir1","recursive_mode":"Recursive","watch_count":12,"health":"Degraded"}"#);
+
+    let deserialized: WatchInfo = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, info);
+}
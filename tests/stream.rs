@@ -0,0 +1,107 @@
+#![cfg(feature = "futures")]
+#![allow(dead_code)]
+
+extern crate futures;
+extern crate notify;
+extern crate tempdir;
+extern crate time;
+
+mod utils;
+
+use futures::executor::block_on_stream;
+use notify::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tempdir::TempDir;
+
+use utils::*;
+
+#[test]
+fn raw_stream() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, events) = stream::channel(16);
+    let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create("file1");
+
+    let mut events = block_on_stream(events);
+    match events.next() {
+        Some(RawEvent { path: Some(path), op: Ok(op), .. }) => {
+            assert_eq!(path, tdir.mkpath("file1"));
+            assert_eq!(op, op::CREATE);
+        }
+        event => panic!("unexpected event: {:?}", event),
+    }
+
+    drop(watcher);
+
+    // the stream ends once the watcher is gone
+    for _ in events {}
+}
+
+#[test]
+fn debounced_stream() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    sleep_macos(10);
+
+    let (tx, events) = stream::channel(16);
+    let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_millis(200)).expect("failed to create debounced watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create("file1");
+
+    let mut events = block_on_stream(events);
+    assert_eq!(events.next(), Some(DebouncedEvent::Create(tdir.mkpath("file1"))));
+
+    drop(watcher);
+
+    assert_eq!(events.next(), None);
+}
+
+#[test]
+fn backpressure() {
+    let (mut tx, events) = stream::channel(2);
+    let sent = Arc::new(AtomicUsize::new(0));
+
+    let sent_thread = sent.clone();
+    let sender = thread::spawn(move || {
+        for i in 0..4 {
+            tx.handle_event(i);
+            sent_thread.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    sleep(100);
+    assert_eq!(sent.load(Ordering::SeqCst), 2);
+
+    let mut events = block_on_stream(events);
+    assert_eq!(events.next(), Some(0));
+    assert_eq!(events.next(), Some(1));
+    assert_eq!(events.next(), Some(2));
+    assert_eq!(events.next(), Some(3));
+
+    sender.join().unwrap();
+    assert_eq!(events.next(), None);
+}
+
+#[test]
+fn cancellation() {
+    let (mut tx, events) = stream::channel(1);
+
+    let sender = thread::spawn(move || {
+        for i in 0..4 {
+            tx.handle_event(i);
+        }
+    });
+
+    sleep(100);
+    drop(events);
+
+    // dropping the stream unblocks the sender
+    sender.join().unwrap();
+}
@@ -3,7 +3,7 @@ use tempdir::TempDir;
 use notify::*;
 
 use std::fs;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::path::PathBuf;
 use std::sync::mpsc::{Receiver, TryRecvError};
 use std::thread;
@@ -24,7 +24,10 @@ pub fn recv_events_with_timeout(rx: &Receiver<RawEvent>, timeout: f64) ->  Vec<(
 
     while time::precise_time_s() < deadline {
         match rx.try_recv() {
-            Ok(RawEvent{path: Some(path), op: Ok(op), cookie}) => {
+            Ok(RawEvent{path: Some(path), op: Ok(op), kind, cookie, ..}) => {
+                if kind != EventKind::Any {
+                    assert_eq!(Op::from(kind), op, "kind {:?} doesn't match op", kind);
+                }
                 evs.push((path, op, cookie));
             },
             Ok(RawEvent{path: None, ..})  => (),
@@ -117,6 +120,8 @@ pub trait TestHelpers {
     fn chmod(&self, p: &str);
     /// Write some data to a file
     fn write(&self, p: &str);
+    /// Read the contents of a file
+    fn read(&self, p: &str);
     /// Remove file or directory
     fn remove(&self, p: &str);
 }
@@ -193,6 +198,15 @@ impl TestHelpers for TempDir {
         file.sync_all().expect("failed to sync file");
     }
 
+    fn read(&self, p: &str) {
+        let path = self.mkpath(p);
+
+        let mut file = fs::File::open(path).expect("failed to open file");
+
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).expect("failed to read file");
+    }
+
     fn remove(&self, p: &str) {
         let path = self.mkpath(p);
         if path.is_dir() {
@@ -5,13 +5,15 @@ extern crate time;
 mod utils;
 
 use notify::*;
+use notify::event::{AccessKind, AccessMode, CreateKind, MetadataKind, ModifyKind, RemoveKind};
 use std::sync::mpsc;
 use tempdir::TempDir;
 use std::thread;
 use std::env;
-
-#[cfg(all(feature = "manual_tests", target_os="linux"))]
+use std::iter;
+use std::path::PathBuf;
 use std::time::Duration;
+
 #[cfg(all(feature = "manual_tests", target_os="linux"))]
 use std::io::prelude::*;
 #[cfg(all(feature = "manual_tests", target_os="linux"))]
@@ -58,6 +60,204 @@ fn new_recommended() {
     assert!(w.is_ok());
 }
 
+#[test]
+fn config_unsupported_option() {
+    let (tx, _) = mpsc::channel();
+    let config = Config::new().with_debounce_delay(Duration::from_secs(1));
+    match PollWatcher::new_raw_with_config(tx, config) {
+        Err(Error::UnsupportedOption("debounce_delay")) => (),
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("debounce delay accepted in raw mode"),
+    }
+
+    let (tx, _) = mpsc::channel();
+    let config = Config::new()
+        .with_thread_name("poll watcher")
+        .with_symlink_policy(SymlinkPolicy::NoFollow);
+    assert!(PollWatcher::new_raw_with_config(tx, config).is_ok());
+
+    let (tx, _) = mpsc::channel();
+    let config = Config::new()
+        .with_debounce_delay(Duration::from_secs(1))
+        .with_relative_paths(true);
+    match PollWatcher::new_with_config(tx, config) {
+        Err(Error::UnsupportedOption("relative_paths")) => (),
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("relative paths accepted in debounced mode"),
+    }
+
+    let (tx, _) = mpsc::channel();
+    let config = Config::new().with_rename_timeout(Duration::from_millis(100));
+    match PollWatcher::new_raw_with_config(tx, config) {
+        Err(Error::UnsupportedOption("rename_timeout")) => (),
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("rename timeout accepted by the poll watcher"),
+    }
+
+    let (tx, _) = mpsc::channel();
+    let config = Config::new().with_access_events(true);
+    match PollWatcher::new_raw_with_config(tx, config) {
+        Err(Error::UnsupportedOption("access_events")) => (),
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("access events accepted by the poll watcher"),
+    }
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn config_unsupported_option_inotify() {
+    let (tx, _) = mpsc::channel();
+    let config = Config::new().with_poll_interval(Duration::from_secs(1));
+    match INotifyWatcher::new_raw_with_config(tx, config) {
+        Err(Error::UnsupportedOption("poll_interval")) => (),
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("poll interval accepted by inotify"),
+    }
+}
+
+#[test]
+fn config_missing_debounce_delay() {
+    let (tx, _) = mpsc::channel();
+    let w: Result<RecommendedWatcher> = Watcher::new_with_config(tx, Config::new());
+    assert!(w.is_err());
+}
+
+// all tests depending on NOTIFY_BACKEND are in here, as the environment is shared between threads
+#[test]
+fn new_fallback() {
+    let (tx, _) = mpsc::channel();
+    let w: FallbackWatcher = Watcher::new_raw(tx).expect("failed to create fallback watcher");
+    assert_eq!(w.backend(), Backend::native());
+
+    env::set_var("NOTIFY_BACKEND", "poll");
+    let (tx, _) = mpsc::channel();
+    let w: Result<FallbackWatcher> = Watcher::new(tx, Duration::from_secs(1));
+    assert_eq!(w.expect("failed to create fallback watcher").backend(), Backend::Poll);
+
+    // the options of the native backends are left out of the config of the poll watcher
+    let config = Config::new()
+        .with_rename_timeout(Duration::from_millis(100))
+        .with_access_events(true);
+    let (tx, _) = mpsc::channel();
+    let w: Result<FallbackWatcher> = Watcher::new_raw_with_config(tx, config.clone());
+    assert_eq!(w.expect("failed to create fallback watcher").backend(), Backend::Poll);
+    let (tx, _) = mpsc::channel();
+    let w: Result<FallbackWatcher> = Watcher::new_with_config(tx, config.with_debounce_delay(Duration::from_secs(1)));
+    assert_eq!(w.expect("failed to create fallback watcher").backend(), Backend::Poll);
+
+    env::set_var("NOTIFY_BACKEND", "native");
+    let (tx, _) = mpsc::channel();
+    let w: Result<FallbackWatcher> = Watcher::new_raw(tx);
+    assert_eq!(w.expect("failed to create fallback watcher").backend(), Backend::native());
+
+    env::set_var("NOTIFY_BACKEND", "invalid");
+    let (tx, _) = mpsc::channel();
+    let w: Result<FallbackWatcher> = Watcher::new_raw(tx);
+    assert!(w.is_err());
+
+    env::remove_var("NOTIFY_BACKEND");
+
+    let (tx, _) = mpsc::channel();
+    let mut w: FallbackWatcher = Watcher::new_raw(tx).expect("failed to create fallback watcher");
+    // a missing path isn't a reason to fall back
+    if w.backend() != Backend::Poll {
+        assert!(w.watch("/some/non/existent/path", RecursiveMode::Recursive).is_err());
+        assert_eq!(w.backend(), Backend::native());
+    }
+}
+
+#[test]
+#[cfg(all(feature = "manual_tests", target_os="linux"))]
+// Test preparation:
+// 1. Lower the limit to a few watches more than the ones in use, e.g. run
+//    `sudo sh -c 'echo 20 > /proc/sys/fs/inotify/max_user_watches'`
+// 2. Run this test on its own, other tests need more watches
+fn fallback_watch_failed() {
+    let mut max_user_watches = String::new();
+    let mut f = File::open("/proc/sys/fs/inotify/max_user_watches").expect("failed to open max_user_watches");
+    f.read_to_string(&mut max_user_watches).expect("failed to read max_user_watches");
+    let max_user_watches: usize = max_user_watches.trim().parse().expect("failed to parse max_user_watches");
+    assert!(max_user_watches <= 100);
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    // more directories than inotify can watch
+    let dirs: Vec<String> = (0..max_user_watches).map(|i| format!("dir1/dir{}", i)).collect();
+    tdir.create_all(dirs.iter().map(|dir| dir.as_ref()).collect());
+    tdir.create_all(vec!["file1"]);
+
+    let (tx, rx) = mpsc::channel();
+    let mut w: FallbackWatcher = Watcher::new_raw(tx).expect("failed to create fallback watcher");
+    assert_eq!(w.backend(), Backend::native());
+    let _watch1 = w.watch(tdir.mkpath("file1"), RecursiveMode::NonRecursive).expect("failed to watch file");
+
+    // a watch that can't be moved to the poll watcher keeps the native watcher
+    tdir.remove("file1");
+    assert!(w.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).is_err());
+    assert_eq!(w.backend(), Backend::native());
+    let info = w.watch_info().expect("failed to get watch info");
+    assert_eq!(info.len(), 1);
+    assert_eq!(info[0].path, tdir.mkpath("file1"));
+
+    // the native watcher runs out of watches, the watched paths are moved to the poll watcher
+    tdir.create("file1");
+    let _watch2 = w.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+    assert_eq!(w.backend(), Backend::Poll);
+
+    let mut paths: Vec<PathBuf> = w.watch_info()
+        .expect("failed to get watch info")
+        .into_iter()
+        .map(|info| info.path)
+        .collect();
+    paths.sort();
+    assert_eq!(paths, vec![tdir.mkpath("dir1"), tdir.mkpath("file1")]);
+
+    rx.try_iter().count();
+    tdir.create("dir1/file2");
+    w.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1/file2"), op::CREATE),
+    ]);
+}
+
+#[test]
+fn watch_missing_path() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, _) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    match watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive) {
+        Err(Error::PathNotFound(path)) => assert_eq!(path, tdir.mkpath("dir1")),
+        r => panic!("unexpected result: {:?}", r),
+    }
+
+    let (tx, _) = mpsc::channel();
+    let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
+    match watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive) {
+        Err(Error::PathNotFound(path)) => assert_eq!(path, tdir.mkpath("dir1")),
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn watch_not_a_directory() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create("file1");
+
+    let (tx, _) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    match watcher.watch(tdir.mkpath("file1/dir1"), RecursiveMode::Recursive) {
+        Err(Error::NotADirectory(path)) => assert_eq!(path, tdir.mkpath("file1/dir1")),
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
+
 // if this test builds, it means RecommendedWatcher is Send.
 #[test]
 fn test_watcher_send() {
@@ -66,7 +266,7 @@ fn test_watcher_send() {
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).unwrap();
 
     thread::spawn(move || {
-        watcher.watch(".", RecursiveMode::Recursive).unwrap();
+        let _watch = watcher.watch(".", RecursiveMode::Recursive).unwrap();
     }).join().unwrap();
 }
 
@@ -82,10 +282,64 @@ fn test_watcher_sync() {
 
     thread::spawn(move || {
         let mut watcher = watcher.write().unwrap();
-        watcher.watch(".", RecursiveMode::Recursive).unwrap();
+        let _watch = watcher.watch(".", RecursiveMode::Recursive).unwrap();
     }).join().unwrap();
 }
 
+#[test]
+fn new_closure() {
+    let w: Result<RecommendedWatcher> = Watcher::new_raw(|_: RawEvent| ());
+    assert!(w.is_ok());
+}
+
+#[test]
+fn closure_handler() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    sleep_macos(10);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(move |event: RawEvent| {
+        let _ = tx.send(event);
+    }).expect("failed to create recommended watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    sleep_windows(100);
+
+    tdir.create("file1");
+
+    let actual = recv_events(&rx);
+    assert!(actual.iter().any(|&(ref path, op, _)| *path == tdir.mkpath("file1") && op.contains(op::CREATE)),
+            "missing create event: {:?}", actual);
+}
+
+#[test]
+fn custom_handler() {
+    use std::sync::{Arc, Mutex};
+
+    struct Collector(Arc<Mutex<Vec<PathBuf>>>);
+
+    impl EventHandler<RawEvent> for Collector {
+        fn handle_event(&mut self, event: RawEvent) {
+            if let Some(path) = event.path {
+                self.0.lock().unwrap().push(path);
+            }
+        }
+    }
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let paths = Arc::new(Mutex::new(Vec::new()));
+    let mut watcher = PollWatcher::with_delay_ms(Collector(paths.clone()), 50).expect("failed to create poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create("file1");
+
+    sleep(500);
+
+    assert!(paths.lock().unwrap().contains(&tdir.mkpath("file1")));
+}
+
 #[test]
 fn watch_relative() {
     // both of the following tests set the same environment variable, so they must not run in parallel
@@ -97,7 +351,7 @@ fn watch_relative() {
 
         let (tx, _) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch("dir1", RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch("dir1", RecursiveMode::Recursive).expect("failed to watch directory");
 
         watcher.unwatch("dir1").expect("failed to unwatch directory");
 
@@ -117,7 +371,7 @@ fn watch_relative() {
 
         let (tx, _) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch("file1", RecursiveMode::Recursive).expect("failed to watch file");
+        let _watch = watcher.watch("file1", RecursiveMode::Recursive).expect("failed to watch file");
 
         watcher.unwatch("file1").expect("failed to unwatch file");
 
@@ -138,7 +392,7 @@ fn watch_relative() {
 
         let (tx, _) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch("dir1", RecursiveMode::Recursive).expect("failed to watch directory");
+        let _watch = watcher.watch("dir1", RecursiveMode::Recursive).expect("failed to watch directory");
 
         watcher.unwatch("dir1").expect("failed to unwatch directory");
 
@@ -159,7 +413,7 @@ fn watch_relative() {
 
         let (tx, _) = mpsc::channel();
         let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-        watcher.watch("file1", RecursiveMode::Recursive).expect("failed to watch file");
+        let _watch = watcher.watch("file1", RecursiveMode::Recursive).expect("failed to watch file");
 
         watcher.unwatch("file1").expect("failed to unwatch file");
 
@@ -185,7 +439,7 @@ fn watch_absolute_network_directory() {
 
     let (tx, _) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
 
     watcher.unwatch(tdir.mkpath("dir1")).expect("failed to unwatch directory");
 
@@ -212,7 +466,7 @@ fn watch_absolute_network_file() {
 
     let (tx, _) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch file");
+    let _watch = watcher.watch(tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch file");
 
     watcher.unwatch(tdir.mkpath("file1")).expect("failed to unwatch file");
 
@@ -240,7 +494,7 @@ fn inotify_queue_overflow() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     for i in 0..20 {
         let filename = format!("file{}", i);
@@ -275,7 +529,7 @@ fn watch_recursive_create_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -316,6 +570,221 @@ fn watch_recursive_create_directory() {
     }
 }
 
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_event_kinds() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create("file1");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    let _watch = watcher.watch(&tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create("dir1");
+    tdir.create("file2");
+    tdir.chmod("file1");
+    tdir.remove("file1");
+
+    let mut actual = Vec::new();
+    while let Ok(event) = rx.recv_timeout(Duration::from_millis(100)) {
+        actual.push((event.path.expect("event without path"), event.kind));
+    }
+
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1"), EventKind::Create(CreateKind::Dir)),
+        (tdir.mkpath("file2"), EventKind::Create(CreateKind::File)),
+        (tdir.mkpath("file2"), EventKind::Access(AccessKind::Close(AccessMode::Write))),
+        (tdir.mkpath("file1"), EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any))),
+        (tdir.mkpath("file1"), EventKind::Remove(RemoveKind::File)),
+    ]);
+}
+
+#[test]
+fn poll_event_kinds() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
+    let _watch = watcher.watch(&tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    sleep(100);
+
+    tdir.create("dir1");
+    tdir.create("file1");
+
+    let mut actual = Vec::new();
+    while let Ok(event) = rx.recv_timeout(Duration::from_millis(500)) {
+        let path = event.path.expect("event without path");
+        if path != tdir.mkpath(".") {
+            actual.push((path, event.kind));
+        }
+    }
+    actual.sort_by(|a, b| a.0.cmp(&b.0));
+
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1"), EventKind::Create(CreateKind::Dir)),
+        (tdir.mkpath("file1"), EventKind::Create(CreateKind::File)),
+    ]);
+}
+
+#[test]
+fn watch_filtered() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec![
+        "dir_src",
+        "dir_target/dir_debug",
+    ]);
+
+    sleep_macos(10);
+
+    let mut filter = Filter::new();
+    filter.include("**/*.rs").expect("failed to add include pattern");
+    filter.exclude("dir_target").expect("failed to add exclude pattern");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    let _watch = watcher.watch_filtered(&tdir.mkpath("."), RecursiveMode::Recursive, filter).expect("failed to watch directory");
+
+    sleep_windows(100);
+
+    tdir.create("dir_src/file1.rs");
+    tdir.create("dir_src/file2.txt");
+    tdir.create("dir_target/dir_debug/file3.rs");
+    tdir.create("file4.rs");
+
+    sleep_macos(100);
+
+    let mut actual: Vec<PathBuf> = recv_events(&rx).into_iter().map(|(path, _, _)| path).collect();
+    actual.sort();
+    actual.dedup();
+
+    assert_eq!(actual, vec![
+        tdir.mkpath("dir_src/file1.rs"),
+        tdir.mkpath("file4.rs"),
+    ]);
+}
+
+#[cfg(feature = "ignore")]
+fn write_ignore_file(path: PathBuf, content: &str) {
+    use std::io::Write;
+
+    let mut file = std::fs::File::create(path).expect("failed to create ignore file");
+    file.write_all(content.as_bytes()).expect("failed to write ignore file");
+}
+
+#[test]
+#[cfg(feature = "ignore")]
+fn watch_ignore_files() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec![
+        "dir_ignored",
+        "dir1",
+    ]);
+    write_ignore_file(tdir.mkpath(".gitignore"), "dir_ignored/\n*.log\n");
+    write_ignore_file(tdir.mkpath("dir1/.ignore"), "file3\n");
+
+    sleep_macos(10);
+
+    let mut filter = Filter::new();
+    filter.use_ignore_files(true);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    let _watch = watcher.watch_filtered(&tdir.mkpath("."), RecursiveMode::Recursive, filter).expect("failed to watch directory");
+
+    sleep_windows(100);
+
+    tdir.create("dir_ignored/file1");
+    tdir.create("file2.log");
+    tdir.create("dir1/file3");
+    tdir.create("dir1/file4");
+
+    sleep_macos(100);
+
+    let mut actual: Vec<PathBuf> = recv_events(&rx).into_iter().map(|(path, _, _)| path).collect();
+    actual.sort();
+    actual.dedup();
+
+    assert_eq!(actual, vec![
+        tdir.mkpath("dir1/file4"),
+    ]);
+
+    // changes to the ignore files apply immediately
+    write_ignore_file(tdir.mkpath(".gitignore"), "*.log\n");
+
+    sleep(100);
+
+    tdir.create("dir_ignored/file5");
+    tdir.create("file6.log");
+
+    sleep_macos(100);
+
+    let mut actual: Vec<PathBuf> = recv_events(&rx).into_iter().map(|(path, _, _)| path).collect();
+    actual.sort();
+    actual.dedup();
+
+    assert_eq!(actual, vec![
+        tdir.mkpath(".gitignore"),
+        tdir.mkpath("dir_ignored/file5"),
+    ]);
+}
+
+#[test]
+#[cfg(feature = "ignore")]
+fn watch_parent_ignore_files() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    // the temporary directory is the root of a repository
+    tdir.create_all(vec![
+        ".git",
+        "dir1",
+    ]);
+    write_ignore_file(tdir.mkpath(".gitignore"), "*.log\n");
+
+    sleep_macos(10);
+
+    let mut filter = Filter::new();
+    filter.use_ignore_files(true);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    let _watch = watcher.watch_filtered(&tdir.mkpath("dir1"), RecursiveMode::Recursive, filter).expect("failed to watch directory");
+
+    sleep_windows(100);
+
+    tdir.create("dir1/file1.log");
+    tdir.create("dir1/file2.tmp");
+
+    sleep_macos(100);
+
+    let mut actual: Vec<PathBuf> = recv_events(&rx).into_iter().map(|(path, _, _)| path).collect();
+    actual.sort();
+    actual.dedup();
+
+    assert_eq!(actual, vec![
+        tdir.mkpath("dir1/file2.tmp"),
+    ]);
+
+    // changes to the ignore files of the parent directories apply as well
+    write_ignore_file(tdir.mkpath(".gitignore"), "*.tmp\n");
+
+    tdir.create("dir1/file3.log");
+    tdir.create("dir1/file4.tmp");
+
+    sleep_macos(100);
+
+    let mut actual: Vec<PathBuf> = recv_events(&rx).into_iter().map(|(path, _, _)| path).collect();
+    actual.sort();
+    actual.dedup();
+
+    assert_eq!(actual, vec![
+        tdir.mkpath("dir1/file3.log"),
+    ]);
+}
+
 #[test]
 fn watch_recursive_move() {
     let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
@@ -328,7 +797,7 @@ fn watch_recursive_move() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -394,7 +863,7 @@ fn watch_recursive_move_in() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&tdir.mkpath("watch_dir"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&tdir.mkpath("watch_dir"), RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -446,7 +915,7 @@ fn watch_recursive_move_out() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(tdir.mkpath("watch_dir"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("watch_dir"), RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -498,7 +967,7 @@ fn watch_nonrecursive() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::NonRecursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::NonRecursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -536,7 +1005,7 @@ fn watch_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -565,7 +1034,7 @@ fn poll_watch_recursive_create_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep(1100); // PollWatcher has only a resolution of 1 second
 
@@ -588,27 +1057,92 @@ fn poll_watch_recursive_create_directory() {
 }
 
 #[test]
-fn poll_watch_recursive_move() {
+fn poll_watch_filtered() {
     let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
 
     tdir.create_all(vec![
-        "dir1a",
+        "dir_src",
+        "dir_target/dir_debug",
     ]);
 
+    let mut filter = Filter::new();
+    filter.include("**/*.rs").expect("failed to add include pattern");
+    filter.exclude("dir_target").expect("failed to add exclude pattern");
+
     let (tx, rx) = mpsc::channel();
     let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch_filtered(tdir.mkpath("."), RecursiveMode::Recursive, filter).expect("failed to watch directory");
 
     sleep(1100); // PollWatcher has only a resolution of 1 second
 
-    tdir.create("dir1a/file1");
+    tdir.create("dir_src/file1.rs");
+    tdir.create("dir_src/file2.txt");
+    tdir.create("dir_target/dir_debug/file3.rs");
 
-    let mut actual = recv_events(&rx);
-    actual.sort_by(|a, b| a.0.cmp(&b.0));
+    sleep(1100); // PollWatcher has only a resolution of 1 second
 
-    assert_eq!(actual, vec![
-        (tdir.mkpath("dir1a"), op::WRITE, None), // parent directory gets modified
-        (tdir.mkpath("dir1a/file1"), op::CREATE, None),
+    assert_eq!(recv_events(&rx), vec![
+        (tdir.mkpath("dir_src/file1.rs"), op::CREATE, None),
+    ]);
+}
+
+#[test]
+#[cfg(feature = "ignore")]
+fn poll_watch_ignore_files() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec![
+        "dir_ignored",
+        "dir1",
+    ]);
+    write_ignore_file(tdir.mkpath(".gitignore"), "dir_ignored/\n*.log\n");
+
+    let mut filter = Filter::new();
+    filter.use_ignore_files(true);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
+    let _watch = watcher.watch_filtered(tdir.mkpath("."), RecursiveMode::Recursive, filter).expect("failed to watch directory");
+
+    sleep(1100); // PollWatcher has only a resolution of 1 second
+
+    tdir.create("dir_ignored/file1");
+    tdir.create("dir1/file2.log");
+    tdir.create("dir1/file3");
+
+    sleep(1100); // PollWatcher has only a resolution of 1 second
+
+    let mut actual = recv_events(&rx);
+    actual.sort_by(|a, b| a.0.cmp(&b.0));
+
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1"), op::WRITE, None), // parent directory gets modified
+        (tdir.mkpath("dir1/file3"), op::CREATE, None),
+    ]);
+}
+
+#[test]
+fn poll_watch_recursive_move() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec![
+        "dir1a",
+    ]);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    sleep(1100); // PollWatcher has only a resolution of 1 second
+
+    tdir.create("dir1a/file1");
+
+    let mut actual = recv_events(&rx);
+    actual.sort_by(|a, b| a.0.cmp(&b.0));
+
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1a"), op::WRITE, None), // parent directory gets modified
+        (tdir.mkpath("dir1a/file1"), op::CREATE, None),
     ]);
 
     sleep(1100); // PollWatcher has only a resolution of 1 second
@@ -658,7 +1192,7 @@ fn poll_watch_recursive_move_in() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
-    watcher.watch(tdir.mkpath("watch_dir"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("watch_dir"), RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep(1100); // PollWatcher has only a resolution of 1 second
 
@@ -699,7 +1233,7 @@ fn poll_watch_recursive_move_out() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
-    watcher.watch(tdir.mkpath("watch_dir"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("watch_dir"), RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep(1100); // PollWatcher has only a resolution of 1 second
 
@@ -742,7 +1276,7 @@ fn poll_watch_nonrecursive() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
-    watcher.watch(tdir.mkpath("."), RecursiveMode::NonRecursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::NonRecursive).expect("failed to watch directory");
 
     sleep(1100); // PollWatcher has only a resolution of 1 second
 
@@ -773,7 +1307,7 @@ fn poll_watch_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
-    watcher.watch(tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep(1100); // PollWatcher has only a resolution of 1 second
 
@@ -794,7 +1328,7 @@ fn watch_nonexisting() {
 
     let (tx, _) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch file");
+    let _watch = watcher.watch(&tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch file");
 }
 
 #[test]
@@ -809,7 +1343,7 @@ fn unwatch_file() {
 
     let (tx, _) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch file");
+    let _watch = watcher.watch(&tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch file");
 
     match watcher.unwatch(&tdir.mkpath("file1")) {
         Ok(_) => (),
@@ -829,7 +1363,7 @@ fn unwatch_directory() {
 
     let (tx, _) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
 
     match watcher.unwatch(&tdir.mkpath("dir1")) {
         Ok(_) => (),
@@ -866,7 +1400,7 @@ fn self_delete_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch file");
+    let _watch = watcher.watch(&tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch file");
 
     sleep_windows(100);
 
@@ -890,6 +1424,7 @@ fn self_delete_file() {
         assert_eq!(actual, vec![
             (tdir.mkpath("file1"), op::CHMOD, None),
             (tdir.mkpath("file1"), op::REMOVE, None),
+            (tdir.mkpath("file1"), op::WATCH_REMOVED, None),
         ]);
     }
 
@@ -898,11 +1433,8 @@ fn self_delete_file() {
 
         assert_eq!(recv_events(&rx), vec![]);
 
-        match watcher.unwatch(&tdir.mkpath("file1")) {
-            Err(Error::WatchNotFound) => (),
-            Err(e) => panic!("{:?}", e),
-            Ok(o) => panic!("{:?}", o),
-        }
+        // the watch has been removed by the kernel, the path is still watched until unwatched
+        watcher.unwatch(&tdir.mkpath("file1")).expect("failed to unwatch file");
     }
 }
 
@@ -919,7 +1451,7 @@ fn self_delete_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -962,11 +1494,8 @@ fn self_delete_directory() {
     }
 
     if cfg!(not(any(target_os="windows", target_os="macos"))) {
-        match watcher.unwatch(&tdir.mkpath("dir1")) {
-            Err(Error::WatchNotFound) => (),
-            Err(e) => panic!("{:?}", e),
-            Ok(o) => panic!("{:?}", o),
-        }
+        // the watch has been removed by the kernel, the path is still watched until unwatched
+        watcher.unwatch(&tdir.mkpath("dir1")).expect("failed to unwatch directory");
     }
 }
 
@@ -983,7 +1512,7 @@ fn self_rename_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch file");
+    let _watch = watcher.watch(&tdir.mkpath("file1"), RecursiveMode::Recursive).expect("failed to watch file");
 
     sleep_windows(100);
 
@@ -1063,7 +1592,7 @@ fn self_rename_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
 
     sleep_windows(100);
 
@@ -1155,7 +1684,7 @@ fn parent_rename_file() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&tdir.mkpath("dir1/file1"), RecursiveMode::Recursive).expect("failed to watch file");
+    let _watch = watcher.watch(&tdir.mkpath("dir1/file1"), RecursiveMode::Recursive).expect("failed to watch file");
 
     sleep_windows(100);
 
@@ -1231,7 +1760,7 @@ fn parent_rename_directory() {
 
     let (tx, rx) = mpsc::channel();
     let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
-    watcher.watch(&tdir.mkpath("dir1/watch_dir"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch = watcher.watch(&tdir.mkpath("dir1/watch_dir"), RecursiveMode::Recursive).expect("failed to watch directory");
 
     tdir.rename("dir1", "dir2");
 
@@ -1285,3 +1814,1220 @@ fn parent_rename_directory() {
         Ok(o) => panic!("{:?}", o),
     }
 }
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_watch_handles() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    let first = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let second = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    drop(first);
+
+    tdir.create("file1");
+
+    assert_eq!(recv_events(&rx), vec![
+        (tdir.mkpath("file1"), op::CREATE, None),
+        (tdir.mkpath("file1"), op::CLOSE_WRITE, None),
+    ]);
+
+    watcher.unwatch_id(second).expect("failed to unwatch directory");
+
+    tdir.create("file2");
+
+    assert_eq!(recv_events(&rx), vec![]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_watch_handles_filters() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create("dir1");
+
+    let mut filter = Filter::new();
+    filter.include("**/*.rs").expect("failed to add include pattern");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    let non_recursive = watcher.watch(tdir.mkpath("."), RecursiveMode::NonRecursive).expect("failed to watch directory");
+    let recursive = watcher.watch_filtered(tdir.mkpath("."), RecursiveMode::Recursive, filter).expect("failed to watch directory");
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info.iter().map(|info| (info.recursive_mode, info.watch_count)).collect::<Vec<_>>(), vec![
+        (RecursiveMode::Recursive, 2),
+    ]);
+
+    tdir.create_all(vec![
+        "file1.txt",
+        "dir1/file2.txt",
+        "dir1/file3.rs",
+    ]);
+
+    assert_eq!(recv_events(&rx), vec![
+        (tdir.mkpath("file1.txt"), op::CREATE, None),
+        (tdir.mkpath("file1.txt"), op::CLOSE_WRITE, None),
+        (tdir.mkpath("dir1/file3.rs"), op::CREATE, None),
+        (tdir.mkpath("dir1/file3.rs"), op::CLOSE_WRITE, None),
+    ]);
+
+    // the filter and recursive mode of the remaining handle apply again
+    drop(non_recursive);
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info.iter().map(|info| (info.recursive_mode, info.watch_count)).collect::<Vec<_>>(), vec![
+        (RecursiveMode::Recursive, 2),
+    ]);
+
+    tdir.create_all(vec![
+        "file4.txt",
+        "dir1/file5.rs",
+    ]);
+
+    assert_eq!(recv_events(&rx), vec![
+        (tdir.mkpath("dir1/file5.rs"), op::CREATE, None),
+        (tdir.mkpath("dir1/file5.rs"), op::CLOSE_WRITE, None),
+    ]);
+
+    let non_recursive = watcher.watch(tdir.mkpath("."), RecursiveMode::NonRecursive).expect("failed to watch directory");
+    drop(recursive);
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info.iter().map(|info| (info.recursive_mode, info.watch_count)).collect::<Vec<_>>(), vec![
+        (RecursiveMode::NonRecursive, 1),
+    ]);
+
+    tdir.create_all(vec![
+        "file6.txt",
+        "dir1/file7.rs",
+    ]);
+
+    assert_eq!(recv_events(&rx), vec![
+        (tdir.mkpath("file6.txt"), op::CREATE, None),
+        (tdir.mkpath("file6.txt"), op::CLOSE_WRITE, None),
+    ]);
+
+    drop(non_recursive);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_unwatch_nested_root() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create("dir1");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let inner = watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    drop(inner);
+
+    tdir.create("dir1/file1");
+
+    assert_eq!(recv_events(&rx), vec![
+        (tdir.mkpath("dir1/file1"), op::CREATE, None),
+        (tdir.mkpath("dir1/file1"), op::CLOSE_WRITE, None),
+    ]);
+}
+
+#[test]
+fn poll_watch_handles() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
+    let first = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let second = first.clone();
+
+    sleep(100);
+
+    drop(first);
+
+    tdir.create("file1");
+
+    let actual: Vec<PathBuf> = recv_events(&rx).into_iter().map(|(path, _, _)| path).collect();
+    assert!(actual.contains(&tdir.mkpath("file1")));
+
+    watcher.unwatch_id(second).expect("failed to unwatch directory");
+
+    tdir.create("file2");
+
+    assert_eq!(recv_events(&rx), vec![]);
+}
+
+#[test]
+fn poll_drop_watch_in_handler() {
+    use std::sync::{Arc, Mutex};
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let handle: Arc<Mutex<Option<WatchId>>> = Arc::new(Mutex::new(None));
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = {
+        let handle = handle.clone();
+        // the watch is removed by the handler as soon as it reports an event
+        PollWatcher::with_delay_ms(move |event: RawEvent| {
+            drop(handle.lock().unwrap().take());
+            let _ = tx.send(event);
+        }, 50).expect("failed to create poll watcher")
+    };
+    *handle.lock().unwrap() = Some(watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory"));
+
+    sleep(100);
+
+    tdir.create("file1");
+
+    // the parent directory may get modified within the resolution of its mtime
+    let event = rx.recv_timeout(Duration::from_secs(1)).expect("no event");
+    assert!(event.path == Some(tdir.mkpath("file1")) || event.path == Some(tdir.mkpath(".")));
+    assert_eq!(watcher.watch_info().expect("failed to get watch info"), vec![]);
+
+    // the rest of the batch has been delivered once the watches could be locked again
+    rx.try_iter().count();
+
+    // the handle may be dropped while flushing as well
+    *handle.lock().unwrap() = Some(watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory"));
+
+    tdir.create("file2");
+    watcher.flush().expect("failed to flush watcher");
+
+    let event = rx.recv_timeout(Duration::from_secs(1)).expect("no event");
+    assert!(event.path == Some(tdir.mkpath("file2")) || event.path == Some(tdir.mkpath(".")));
+    assert_eq!(watcher.watch_info().expect("failed to get watch info"), vec![]);
+}
+
+#[test]
+fn poll_watch_handles_filters() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create("dir1");
+
+    let mut filter = Filter::new();
+    filter.include("**/*.rs").expect("failed to add include pattern");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
+    let _recursive = watcher.watch_filtered(tdir.mkpath("."), RecursiveMode::Recursive, filter).expect("failed to watch directory");
+    let non_recursive = watcher.watch(tdir.mkpath("."), RecursiveMode::NonRecursive).expect("failed to watch directory");
+
+    // the filter and recursive mode of the remaining handle apply again
+    drop(non_recursive);
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info.iter().map(|info| info.recursive_mode).collect::<Vec<_>>(), vec![
+        RecursiveMode::Recursive,
+    ]);
+
+    sleep(100);
+
+    tdir.create_all(vec![
+        "file1.txt",
+        "dir1/file2.rs",
+    ]);
+
+    // the parent directory may get modified within the resolution of its mtime
+    let actual: Vec<PathBuf> = recv_events(&rx)
+        .into_iter()
+        .map(|(path, _, _)| path)
+        .filter(|path| *path != tdir.mkpath("."))
+        .collect();
+    assert_eq!(actual, vec![tdir.mkpath("dir1/file2.rs")]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_watch_info() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec![
+        "dir1/dir11/file1",
+        "dir2/file2",
+    ]);
+
+    let (tx, _) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    let _watch1 = watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch2 = watcher.watch(tdir.mkpath("dir2"), RecursiveMode::NonRecursive).expect("failed to watch directory");
+
+    let mut actual = watcher.watch_info().expect("failed to get watch info");
+    actual.sort_by(|a, b| a.path.cmp(&b.path));
+
+    assert_eq!(actual, vec![
+        WatchInfo {
+            path: tdir.mkpath("dir1"),
+            recursive_mode: RecursiveMode::Recursive,
+            watch_count: 2,
+            health: WatchHealth::Healthy,
+        },
+        WatchInfo {
+            path: tdir.mkpath("dir2"),
+            recursive_mode: RecursiveMode::NonRecursive,
+            watch_count: 1,
+            health: WatchHealth::Healthy,
+        },
+    ]);
+
+    tdir.remove("dir2/file2");
+    tdir.remove("dir2");
+
+    sleep(100);
+
+    let lost: Vec<PathBuf> = watcher.watch_info()
+        .expect("failed to get watch info")
+        .into_iter()
+        .filter(|info| info.health == WatchHealth::Lost)
+        .map(|info| info.path)
+        .collect();
+    assert_eq!(lost, vec![tdir.mkpath("dir2")]);
+}
+
+#[test]
+fn poll_watch_info() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec![
+        "dir1/file1",
+        "dir1/file2",
+    ]);
+
+    let (tx, _) = mpsc::channel();
+    let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    // the directory itself and its two files
+    assert_eq!(watcher.watch_info().expect("failed to get watch info"), vec![
+        WatchInfo {
+            path: tdir.mkpath("dir1"),
+            recursive_mode: RecursiveMode::Recursive,
+            watch_count: 3,
+            health: WatchHealth::Healthy,
+        },
+    ]);
+    assert_eq!(watcher.watched_paths().expect("failed to get watched paths"), vec![tdir.mkpath("dir1")]);
+
+    tdir.remove("dir1/file1");
+    tdir.remove("dir1/file2");
+    tdir.remove("dir1");
+
+    sleep(200);
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info[0].health, WatchHealth::Lost);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_pause_resume() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    watcher.pause().expect("failed to pause watcher");
+
+    tdir.create("dir1");
+
+    sleep(100);
+
+    watcher.resume().expect("failed to resume watcher");
+
+    let event = rx.recv_timeout(Duration::from_millis(500)).expect("no rescan event");
+    assert_eq!((event.path, event.op.unwrap()), (None, op::RESCAN));
+
+    // the new directory has been watched while the watcher was paused
+    tdir.create("dir1/file1");
+
+    assert_eq!(recv_events(&rx), vec![
+        (tdir.mkpath("dir1/file1"), op::CREATE, None),
+        (tdir.mkpath("dir1/file1"), op::CLOSE_WRITE, None),
+    ]);
+
+    // nothing has been missed, there is nothing to rescan
+    watcher.pause().expect("failed to pause watcher");
+    watcher.resume().expect("failed to resume watcher");
+
+    assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+}
+
+#[test]
+fn poll_pause_resume() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    sleep(100);
+
+    watcher.pause().expect("failed to pause watcher");
+
+    tdir.create("file1");
+
+    sleep(200);
+
+    watcher.resume().expect("failed to resume watcher");
+
+    let event = rx.recv_timeout(Duration::from_millis(500)).expect("no rescan event");
+    assert_eq!((event.path, event.op.unwrap()), (None, op::RESCAN));
+
+    // the new file has been scanned while the watcher was paused
+    assert_eq!(recv_events_with_timeout(&rx, 0.5), vec![]);
+}
+
+#[test]
+fn flush() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    sleep_macos(10);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    sleep_windows(100);
+
+    tdir.create("file1");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<PathBuf> = rx.try_iter().filter_map(|event| event.path).collect();
+    assert!(actual.contains(&tdir.mkpath("file1")));
+}
+
+#[test]
+fn poll_flush() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    // the watched paths won't be scanned again during the test
+    let mut watcher = PollWatcher::with_delay_ms(tx, 60000).expect("failed to create poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create("file1");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .map(|event| (event.path.expect("event without path"), event.op.expect("event error")))
+        .filter(|&(ref path, _)| *path != tdir.mkpath("."))
+        .collect();
+    assert_eq!(actual, vec![(tdir.mkpath("file1"), op::CREATE)]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_batched() {
+    use std::sync::{Arc, Mutex};
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    // the handler blocks the event loop while the lock is held, so the events queued meanwhile
+    // are read at once
+    let gate = Arc::new(Mutex::new(()));
+    let guard = gate.lock().expect("failed to lock gate");
+
+    let (tx, rx) = mpsc::channel();
+    let handler = {
+        let gate = gate.clone();
+        move |batch: Vec<RawEvent>| {
+            let _ = tx.send(batch);
+            drop(gate.lock());
+        }
+    };
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(Batched::new(handler)).expect("failed to create recommended watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create("file1");
+
+    let mut batches = vec![rx.recv().expect("failed to receive batch")];
+
+    tdir.create("file2");
+    tdir.create("file3");
+    drop(guard);
+
+    watcher.flush().expect("failed to flush watcher");
+
+    batches.extend(rx.try_iter());
+    assert_eq!(batches.len(), 2);
+    let actual: Vec<(PathBuf, Op)> = batches.into_iter()
+        .flat_map(|batch| batch.into_iter())
+        .map(|event| (event.path.expect("event without path"), event.op.expect("event error")))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("file1"), op::CREATE),
+        (tdir.mkpath("file1"), op::CLOSE_WRITE),
+        (tdir.mkpath("file2"), op::CREATE),
+        (tdir.mkpath("file2"), op::CLOSE_WRITE),
+        (tdir.mkpath("file3"), op::CREATE),
+        (tdir.mkpath("file3"), op::CLOSE_WRITE),
+    ]);
+}
+
+#[test]
+fn poll_batched() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = PollWatcher::new_raw_with_config(Batched::new(tx),
+                                                       Config::new().with_poll_interval(Duration::from_secs(60)))
+        .expect("failed to create poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create("file1");
+    tdir.create("file2");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let batches: Vec<Vec<RawEvent>> = rx.try_iter().collect();
+    assert_eq!(batches.len(), 1);
+    let mut actual: Vec<PathBuf> = batches[0].iter()
+        .filter(|event| event.op.as_ref().ok() == Some(&op::CREATE))
+        .map(|event| event.path.clone().expect("event without path"))
+        .collect();
+    actual.sort();
+    assert_eq!(actual, vec![tdir.mkpath("file1"), tdir.mkpath("file2")]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_relative_paths() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1"]);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = INotifyWatcher::new_raw_with_config(tx, Config::new().with_relative_paths(true))
+        .expect("failed to create inotify watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _nested = watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create("file1");
+    tdir.create("dir1/file2");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, PathBuf)> = rx.try_iter()
+        .filter(|event| event.op.as_ref().ok() == Some(&op::CREATE))
+        .map(|event| event.relative.expect("event without relative path"))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("."), PathBuf::from("file1")),
+        (tdir.mkpath("dir1"), PathBuf::from("file2")),
+    ]);
+}
+
+#[test]
+fn poll_relative_paths() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::new()
+        .with_poll_interval(Duration::from_secs(60))
+        .with_relative_paths(true);
+    let mut watcher = PollWatcher::new_raw_with_config(tx, config).expect("failed to create poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.create_all(vec!["dir1", "dir1/file1"]);
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let mut actual: Vec<(PathBuf, PathBuf)> = rx.try_iter()
+        .filter(|event| event.op.as_ref().ok() == Some(&op::CREATE))
+        .map(|event| event.relative.expect("event without relative path"))
+        .collect();
+    actual.sort();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("."), PathBuf::from("dir1")),
+        (tdir.mkpath("."), PathBuf::from("dir1/file1")),
+    ]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_symlink_policy() {
+    use std::os::unix::fs::symlink;
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+    let outside = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1", "dir2"]);
+    outside.create_all(vec!["dir3"]);
+    symlink(tdir.mkpath("."), tdir.mkpath("dir1/loop")).expect("failed to create symlink");
+    symlink(tdir.mkpath("dir2"), tdir.mkpath("dir1/link2")).expect("failed to create symlink");
+    symlink(outside.mkpath("."), tdir.mkpath("outside")).expect("failed to create symlink");
+
+    // the root and dir1 and dir2 through their own paths, outside and dir3 below it
+    let expected = vec![
+        (SymlinkPolicy::Follow, 5),
+        (SymlinkPolicy::NoFollow, 3),
+        (SymlinkPolicy::StayInsideRoot, 3),
+        (SymlinkPolicy::WatchLink, 3),
+    ];
+    for (policy, watch_count) in expected {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = INotifyWatcher::new_raw_with_config(tx, Config::new().with_symlink_policy(policy))
+            .expect("failed to create inotify watcher");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+        let info = watcher.watch_info().expect("failed to get watch info");
+        assert_eq!(info[0].watch_count, watch_count, "{:?}", policy);
+
+        tdir.create("dir2/file1");
+        outside.create("dir3/file2");
+
+        watcher.flush().expect("failed to flush watcher");
+
+        let mut actual: Vec<PathBuf> = rx.try_iter()
+            .filter(|event| event.op.as_ref().ok() == Some(&op::CREATE))
+            .map(|event| event.path.expect("event without path"))
+            .collect();
+        actual.sort();
+        let mut expected = vec![tdir.mkpath("dir2/file1")];
+        if policy == SymlinkPolicy::Follow {
+            expected.push(tdir.mkpath("outside/dir3/file2"));
+        }
+        assert_eq!(actual, expected, "{:?}", policy);
+
+        tdir.remove("dir2/file1");
+        outside.remove("dir3/file2");
+    }
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_watch_link() {
+    use std::fs;
+    use std::os::unix::fs::symlink;
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1"]);
+    symlink(tdir.mkpath("dir1"), tdir.mkpath("link1")).expect("failed to create symlink");
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::new().with_symlink_policy(SymlinkPolicy::WatchLink);
+    let mut watcher = INotifyWatcher::new_raw_with_config(tx, config).expect("failed to create inotify watcher");
+    let _watch = watcher.watch(tdir.mkpath("link1"), RecursiveMode::Recursive).expect("failed to watch link");
+
+    // changes inside the target aren't reported, removing the link is
+    tdir.create("dir1/file1");
+    fs::remove_file(tdir.mkpath("link1")).expect("failed to remove symlink");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("link1"), op::CHMOD),
+        (tdir.mkpath("link1"), op::REMOVE),
+        (tdir.mkpath("link1"), op::WATCH_REMOVED),
+    ]);
+}
+
+#[test]
+#[cfg(unix)]
+fn poll_symlink_policy() {
+    use std::os::unix::fs::symlink;
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+    let outside = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1", "dir1/file1"]);
+    outside.create_all(vec!["file2"]);
+    symlink(tdir.mkpath("."), tdir.mkpath("dir1/loop")).expect("failed to create symlink");
+    symlink(outside.mkpath("."), tdir.mkpath("outside")).expect("failed to create symlink");
+
+    // the root, dir1, file1 and both links, file2 below the outside link
+    let expected = vec![
+        (SymlinkPolicy::Follow, 6),
+        (SymlinkPolicy::NoFollow, 5),
+        (SymlinkPolicy::StayInsideRoot, 5),
+    ];
+    for (policy, watch_count) in expected {
+        let (tx, _) = mpsc::channel();
+        let config = Config::new()
+            .with_poll_interval(Duration::from_secs(60))
+            .with_symlink_policy(policy);
+        let mut watcher = PollWatcher::new_raw_with_config(tx, config).expect("failed to create poll watcher");
+        let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+        let info = watcher.watch_info().expect("failed to get watch info");
+        assert_eq!(info[0].watch_count, watch_count, "{:?}", policy);
+    }
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_pending_watch() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::new().with_pending_watches(true);
+    let mut watcher = INotifyWatcher::new_raw_with_config(tx, config).expect("failed to create inotify watcher");
+    let _watch = watcher.watch(tdir.mkpath("dir1/file1"), RecursiveMode::Recursive).expect("failed to watch missing path");
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info[0].health, WatchHealth::Pending);
+
+    // changes of the ancestors aren't reported
+    tdir.create("file2");
+    tdir.create("dir1");
+
+    // the file is only reported as it is written if its directory has been watched already
+    watcher.flush().expect("failed to flush watcher");
+
+    tdir.create("dir1/file3");
+    tdir.create("dir1/file1");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    tdir.write("dir1/file1");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1/file1"), op::CREATE),
+        (tdir.mkpath("dir1/file1"), op::CLOSE_WRITE),
+        (tdir.mkpath("dir1/file1"), op::WRITE),
+        (tdir.mkpath("dir1/file1"), op::CLOSE_WRITE),
+    ]);
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info[0].health, WatchHealth::Healthy);
+}
+
+#[test]
+fn poll_watch_missing_path() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = PollWatcher::with_delay_ms(tx, 50).expect("failed to create poll watcher");
+    assert!(watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).is_err());
+
+    assert!(watcher.watch_info().expect("failed to get watch info").is_empty());
+    watcher.flush().expect("failed to flush watcher");
+    assert!(rx.try_recv().is_err());
+}
+
+#[test]
+fn poll_pending_watch() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::new()
+        .with_poll_interval(Duration::from_secs(60))
+        .with_pending_watches(true);
+    let mut watcher = PollWatcher::new_raw_with_config(tx, config).expect("failed to create poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch missing path");
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info[0].health, WatchHealth::Pending);
+
+    watcher.flush().expect("failed to flush watcher");
+    assert!(rx.try_recv().is_err());
+
+    tdir.create_all(vec!["dir1", "dir1/file1"]);
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let mut actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    actual.sort();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1"), op::CREATE),
+        (tdir.mkpath("dir1/file1"), op::CREATE),
+    ]);
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info[0].health, WatchHealth::Healthy);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_persistent_root() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1", "file2"]);
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::new().with_persistent_roots(true);
+    let mut watcher = INotifyWatcher::new_raw_with_config(tx, config).expect("failed to create inotify watcher");
+    let _watch1 = watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch2 = watcher.watch(tdir.mkpath("file2"), RecursiveMode::Recursive).expect("failed to watch file");
+
+    tdir.remove("dir1");
+    tdir.remove("file2");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let mut health: Vec<WatchHealth> = watcher.watch_info()
+        .expect("failed to get watch info")
+        .into_iter()
+        .map(|info| info.health)
+        .collect();
+    health.dedup();
+    assert_eq!(health, vec![WatchHealth::Pending]);
+
+    tdir.create("dir1");
+    tdir.create("file2");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    tdir.create("dir1/file1");
+    tdir.write("file2");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1"), op::REMOVE),
+        (tdir.mkpath("dir1"), op::WATCH_REMOVED),
+        (tdir.mkpath("file2"), op::CHMOD),
+        (tdir.mkpath("file2"), op::REMOVE),
+        (tdir.mkpath("file2"), op::WATCH_REMOVED),
+        (tdir.mkpath("dir1"), op::CREATE),
+        (tdir.mkpath("file2"), op::CREATE),
+        (tdir.mkpath("file2"), op::CLOSE_WRITE),
+        (tdir.mkpath("dir1/file1"), op::CREATE),
+        (tdir.mkpath("dir1/file1"), op::CLOSE_WRITE),
+        (tdir.mkpath("file2"), op::WRITE),
+        (tdir.mkpath("file2"), op::CLOSE_WRITE),
+    ]);
+}
+
+#[test]
+fn poll_persistent_root() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1", "dir1/file1"]);
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::new()
+        .with_poll_interval(Duration::from_secs(60))
+        .with_persistent_roots(true);
+    let mut watcher = PollWatcher::new_raw_with_config(tx, config).expect("failed to create poll watcher");
+    let _watch = watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.remove("dir1/file1");
+    tdir.remove("dir1");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info[0].health, WatchHealth::Pending);
+
+    // nothing is reported while the path is missing
+    watcher.flush().expect("failed to flush watcher");
+
+    tdir.create("dir1");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let mut actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .map(|RawEvent { path, op, .. }| (path.expect("event without path"), op.expect("event error")))
+        .collect();
+    // the order of the removals is arbitrary
+    actual[..2].sort();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1"), op::REMOVE),
+        (tdir.mkpath("dir1/file1"), op::REMOVE),
+        (tdir.mkpath("dir1"), op::CREATE),
+    ]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_rename_watched_directory() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1/dir11/dir111"]);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new_raw(tx).expect("failed to create recommended watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _nested = watcher.watch(tdir.mkpath("dir1/dir11"), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.rename("dir1", "dir2");
+    tdir.create("dir2/dir11/dir111/file1");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1"), op::RENAME),
+        (tdir.mkpath("dir2"), op::RENAME),
+        (tdir.mkpath("dir2/dir11/dir111/file1"), op::CREATE),
+        (tdir.mkpath("dir2/dir11/dir111/file1"), op::CLOSE_WRITE),
+    ]);
+
+    // the nested watched path has been moved along with its parent
+    let mut actual: Vec<(PathBuf, usize, WatchHealth)> = watcher.watch_info()
+        .expect("failed to get watch info")
+        .into_iter()
+        .map(|info| (info.path, info.watch_count, info.health))
+        .collect();
+    actual.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(actual, vec![
+        (tdir.mkpath("."), 4, WatchHealth::Healthy),
+        (tdir.mkpath("dir2/dir11"), 2, WatchHealth::Healthy),
+    ]);
+
+    watcher.unwatch(&tdir.mkpath("dir2/dir11")).expect("failed to unwatch renamed directory");
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_rename_timeout() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1/file1"]);
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::new().with_rename_timeout(Duration::from_millis(500));
+    let mut watcher = INotifyWatcher::new_raw_with_config(tx, config).expect("failed to create inotify watcher");
+    let _watch = watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.rename("dir1/file1", "file1");
+
+    // the move waits for its matching half before being reported as a removal
+    assert_eq!(recv_events_with_timeout(&rx, 0.2), vec![]);
+    assert_eq!(recv_events_with_timeout(&rx, 1.0), vec![
+        (tdir.mkpath("dir1/file1"), op::REMOVE, None),
+    ]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_rename_across_reads() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    // names of different lengths, so that the halves of some renames end up in separate reads
+    let names: Vec<String> = (0..100).map(|i| format!("file_{}{}", i, iter::repeat("x").take(i % 23).collect::<String>())).collect();
+    for name in &names {
+        tdir.create(name);
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = INotifyWatcher::new_raw(tx).expect("failed to create inotify watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    for name in &names {
+        tdir.rename(name, &format!("{}_renamed", name));
+    }
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    let expected: Vec<(PathBuf, Op)> = names.iter()
+        .flat_map(|name| vec![
+            (tdir.mkpath(name), op::RENAME),
+            (tdir.mkpath(&format!("{}_renamed", name)), op::RENAME),
+        ])
+        .collect();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_moved_in_directory_contents() {
+    use std::fs;
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+    let outside = TempDir::new("outside").expect("failed to create temporary directory");
+
+    outside.create_all(vec!["dir1/dir11/file1"]);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = INotifyWatcher::new_raw(tx).expect("failed to create inotify watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    fs::rename(outside.mkpath("dir1"), tdir.mkpath("dir1")).expect("failed to move directory");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, EventKind)> = rx.try_iter()
+        .filter_map(|RawEvent { path, kind, .. }| path.map(|path| (path, kind)))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1"), EventKind::Create(CreateKind::Dir)),
+        (tdir.mkpath("dir1/dir11"), EventKind::Create(CreateKind::Dir)),
+        (tdir.mkpath("dir1/dir11/file1"), EventKind::Create(CreateKind::File)),
+    ]);
+
+    // the contents are watched as well
+    tdir.write("dir1/dir11/file1");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1/dir11/file1"), op::WRITE),
+        (tdir.mkpath("dir1/dir11/file1"), op::CLOSE_WRITE),
+    ]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_access_events() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["file1"]);
+    tdir.write("file1");
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::new().with_access_events(true);
+    let mut watcher = INotifyWatcher::new_raw_with_config(tx, config).expect("failed to create inotify watcher");
+    let _watch = watcher.watch(tdir.mkpath("."), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.read("file1");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op, EventKind)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, kind, .. }| path.map(|path| (path, op.expect("event error"), kind)))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("file1"), op::OPEN, EventKind::Access(AccessKind::Open)),
+        (tdir.mkpath("file1"), op::ACCESS, EventKind::Access(AccessKind::Read)),
+        (tdir.mkpath("file1"), op::CLOSE_NOWRITE, EventKind::Access(AccessKind::Close(AccessMode::Read))),
+    ]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_access_events_per_watch() {
+    use notify::inotify::flags;
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1/file1", "dir2/file2"]);
+    tdir.write("dir1/file1");
+    tdir.write("dir2/file2");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = INotifyWatcher::new_raw(tx).expect("failed to create inotify watcher");
+    let _watch1 = watcher.watch_with_mask(tdir.mkpath("dir1"), RecursiveMode::Recursive, flags::IN_ALL_EVENTS).expect("failed to watch directory");
+    let _watch2 = watcher.watch(tdir.mkpath("dir2"), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    tdir.read("dir1/file1");
+    tdir.read("dir2/file2");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1/file1"), op::OPEN),
+        (tdir.mkpath("dir1/file1"), op::ACCESS),
+        (tdir.mkpath("dir1/file1"), op::CLOSE_NOWRITE),
+    ]);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_watch_with_mask() {
+    use notify::inotify::flags;
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1"]);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = INotifyWatcher::new_raw(tx).expect("failed to create inotify watcher");
+    let _watch = watcher.watch_with_mask(tdir.mkpath("."), RecursiveMode::Recursive, flags::IN_CREATE)
+        .expect("failed to watch directory");
+
+    tdir.create("dir1/file1");
+    tdir.write("dir1/file1");
+    tdir.remove("dir1/file1");
+
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1/file1"), op::CREATE),
+    ]);
+
+    tdir.create("file2");
+
+    let result = watcher.watch_with_mask(tdir.mkpath("file2"), RecursiveMode::NonRecursive, flags::IN_CREATE | flags::IN_ONLYDIR);
+    match result {
+        Err(Error::NotADirectory(ref path)) if *path == tdir.mkpath("file2") => (),
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("file accepted with IN_ONLYDIR"),
+    }
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_failed_recursive_watch() {
+    use notify::inotify::flags;
+    use std::os::unix::fs::symlink;
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1/dir11", "dir2"]);
+    // the link is followed while walking, but it isn't a directory to inotify with IN_DONT_FOLLOW
+    symlink(tdir.mkpath("dir2"), tdir.mkpath("dir1/link2")).expect("failed to create symlink");
+
+    let (tx, _) = mpsc::channel();
+    let mut watcher = INotifyWatcher::new_raw(tx).expect("failed to create inotify watcher");
+    let mask = flags::IN_CREATE | flags::IN_DONT_FOLLOW | flags::IN_ONLYDIR;
+    let result = watcher.watch_with_mask(tdir.mkpath("dir1"), RecursiveMode::Recursive, mask);
+    match result {
+        Err(Error::NotADirectory(ref path)) if *path == tdir.mkpath("dir1/link2") => (),
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("link accepted with IN_DONT_FOLLOW and IN_ONLYDIR"),
+    }
+
+    assert_eq!(watcher.watch_info().expect("failed to get watch info"), vec![]);
+
+    // the watches added before the failure have been removed, the excluded directory isn't
+    // watched by the parent
+    let mut filter = Filter::new();
+    filter.exclude("dir1").expect("failed to add exclude pattern");
+    let _watch = watcher.watch_filtered(tdir.mkpath("."), RecursiveMode::Recursive, filter).expect("failed to watch directory");
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info.len(), 1);
+    assert_eq!(info[0].watch_count, 2);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_watch_with_mask_oneshot() {
+    use notify::inotify::flags;
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["file1"]);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = INotifyWatcher::new_raw(tx).expect("failed to create inotify watcher");
+    let _watch = watcher.watch_with_mask(tdir.mkpath("file1"), RecursiveMode::NonRecursive, flags::IN_MODIFY | flags::IN_ONESHOT)
+        .expect("failed to watch file");
+
+    tdir.write("file1");
+    watcher.flush().expect("failed to flush watcher");
+    tdir.write("file1");
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("file1"), op::WRITE),
+        (tdir.mkpath("file1"), op::WATCH_REMOVED),
+    ]);
+
+    // the kernel has removed the watch after its first event
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info[0].watch_count, 0);
+    assert_eq!(info[0].health, WatchHealth::Lost);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn inotify_watch_removed() {
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1/dir2", "file1"]);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = INotifyWatcher::new_raw(tx).expect("failed to create inotify watcher");
+    let _watch1 = watcher.watch(tdir.mkpath("dir1"), RecursiveMode::Recursive).expect("failed to watch directory");
+    let _watch2 = watcher.watch(tdir.mkpath("file1"), RecursiveMode::NonRecursive).expect("failed to watch file");
+
+    // the watch of a removed sub-directory is gone, the watched path is still healthy
+    tdir.remove("dir1/dir2");
+    watcher.flush().expect("failed to flush watcher");
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    let dir1 = info.iter().find(|info| info.path == tdir.mkpath("dir1")).expect("dir1 isn't watched");
+    assert_eq!(dir1.watch_count, 1);
+    assert_eq!(dir1.health, WatchHealth::Healthy);
+
+    tdir.remove("file1");
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1/dir2"), op::REMOVE),
+        (tdir.mkpath("file1"), op::CHMOD),
+        (tdir.mkpath("file1"), op::REMOVE),
+        (tdir.mkpath("file1"), op::WATCH_REMOVED),
+    ]);
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    let file1 = info.iter().find(|info| info.path == tdir.mkpath("file1")).expect("file1 isn't watched");
+    assert_eq!(file1.health, WatchHealth::Lost);
+
+    // a path whose watch has been removed by the kernel can be unwatched all the same
+    watcher.unwatch(tdir.mkpath("file1")).expect("failed to unwatch file");
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert!(info.iter().all(|info| info.path != tdir.mkpath("file1")));
+
+    // removing the remaining watches doesn't touch the ones the kernel has removed
+    watcher.unwatch(tdir.mkpath("dir1")).expect("failed to unwatch directory");
+}
+
+#[test]
+#[cfg(all(feature = "manual_tests", target_os="linux"))]
+// Test preparation:
+// 1. Run as root, the test mounts a tmpfs inside a temporary directory
+fn inotify_unmount() {
+    use std::process::Command;
+
+    let tdir = TempDir::new("temp_dir").expect("failed to create temporary directory");
+
+    tdir.create_all(vec!["dir1"]);
+    let status = Command::new("mount")
+        .args(&["-t", "tmpfs", "tmpfs"])
+        .arg(tdir.mkpath("dir1"))
+        .status()
+        .expect("failed to run mount");
+    assert!(status.success());
+    tdir.create_all(vec!["dir1/dir2"]);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = INotifyWatcher::new_raw(tx).expect("failed to create inotify watcher");
+    let _watch = watcher.watch(tdir.mkpath("dir1/dir2"), RecursiveMode::Recursive).expect("failed to watch directory");
+
+    let status = Command::new("umount").arg(tdir.mkpath("dir1")).status().expect("failed to run umount");
+    assert!(status.success());
+    watcher.flush().expect("failed to flush watcher");
+
+    let actual: Vec<(PathBuf, Op)> = rx.try_iter()
+        .filter_map(|RawEvent { path, op, .. }| path.map(|path| (path, op.expect("event error"))))
+        .collect();
+    assert_eq!(actual, vec![
+        (tdir.mkpath("dir1/dir2"), op::UNMOUNTED),
+        (tdir.mkpath("dir1/dir2"), op::WATCH_REMOVED),
+    ]);
+
+    let info = watcher.watch_info().expect("failed to get watch info");
+    assert_eq!(info[0].health, WatchHealth::Lost);
+}
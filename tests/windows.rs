@@ -49,7 +49,7 @@ mod windows_tests {
 
             // need the ref, otherwise it's a move and the dir will be dropped!
             for d in &dirs {
-                w.watch(d.path(), RecursiveMode::Recursive).unwrap();
+                let _watch = w.watch(d.path(), RecursiveMode::Recursive).unwrap();
             }
 
             // unwatch half of the directories, let the others get stopped when we go out of scope
@@ -86,7 +86,7 @@ mod windows_tests {
         let mut w = ReadDirectoryChangesWatcher::create(tx, meta_tx).unwrap();
 
         let d = TempDir::new("rsnotifytest").unwrap();
-        w.watch(d.path(), RecursiveMode::Recursive).unwrap();
+        let _watch = w.watch(d.path(), RecursiveMode::Recursive).unwrap();
 
         // should be at least one awaken in there
         const TIMEOUT_S: f64 = 5.0;
@@ -119,7 +119,7 @@ mod windows_tests {
             {
                 let (meta_tx, _) = mpsc::channel();
                 let mut w = ReadDirectoryChangesWatcher::create(tx, meta_tx).unwrap();
-                w.watch(d.path(), RecursiveMode::Recursive).unwrap();
+                let _watch = w.watch(d.path(), RecursiveMode::Recursive).unwrap();
                 thread::sleep(Duration::from_millis(1)); // this should make us run pretty hot but not insane
             }
             wait_for_disconnect(&rx);